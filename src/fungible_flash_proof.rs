@@ -0,0 +1,183 @@
+use scrypto::prelude::*;
+use crate::types::FeeInfo;
+
+#[blueprint]
+mod fungible_flash_proof {
+    enable_method_auth! {
+        roles {
+            operator => updatable_by: [OWNER];
+        },
+        methods {
+            withdraw_deposit => restrict_to: [OWNER];
+            withdraw_fees => restrict_to: [OWNER];
+            update_fee => restrict_to: [operator, OWNER];
+            update_max_amount => restrict_to: [operator, OWNER];
+            update_end_timestamp => restrict_to: [operator, OWNER];
+            get_proof => PUBLIC;
+        }
+    }
+
+    struct FungibleFlashProof {
+        owner_resource: ResourceAddress,
+        deposit_vault: FungibleVault,
+        max_amount: Decimal,
+        fee_info: Option<FeeInfo>,
+        fee_vault: Option<Vault>,
+        end_timestamp: Instant,
+    }
+
+    impl FungibleFlashProof {
+        // Instantiates a component holding a fungible deposit, of which Proofs of up to max_amount
+        // can be generated. If a fee is set, its amount is the price per unit proven.
+        pub fn instantiate(
+            deposit: FungibleBucket,
+            max_amount: Decimal,
+            fee_info: Option<FeeInfo>,
+            end_timestamp: Instant,
+            operator_rule: Option<AccessRule>,
+        ) -> (Global<FungibleFlashProof>, FungibleBucket) {
+            // Get an address reservation which we'll use in the description of the owner resource
+            let (address_reservation, component_address) = Runtime::allocate_component_address(FungibleFlashProof::blueprint_id());
+
+            // If a fee is asked, make sure it's a fungible and the amount is higher than 0
+            let mut vault = None;
+            if let Some(ref fee_info) = fee_info {
+                assert!(fee_info.resource.is_fungible(), "Fee resource must be fungible");
+                assert!(
+                    fee_info.amount > Decimal::ZERO,
+                    "Fee amount must be higher than 0"
+                );
+
+                // Create a Vault to store the fee payments in
+                vault = Some(Vault::new(fee_info.resource));
+            };
+
+            assert!(deposit.amount() > Decimal::ZERO, "Must supply a deposit!");
+            assert!(max_amount > Decimal::ZERO, "Max amount must be higher than 0");
+            let deposit_resource = deposit.resource_address();
+
+            // Create an owner badge
+            let owner_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "symbol" => "FLASHOWN", locked;
+                        "name" => "Flash Proof component owner", locked;
+                        "description" => "The owner badge for a fungible Flash Proof component. Can be used to update state on the component, withdraw your deposit and claim fees.", locked;
+                        "deposit_resource" => GlobalAddress::from(deposit_resource), locked;
+                        "component" => GlobalAddress::from(component_address), locked;
+                    }
+                ))
+                .divisibility(DIVISIBILITY_NONE)
+                .mint_initial_supply(1);
+
+            // Instantiate the component and make the supplied owner resource address the owner
+            let component = Self {
+                owner_resource: owner_badge.resource_address(),
+                deposit_vault: FungibleVault::with_bucket(deposit),
+                max_amount,
+                fee_info,
+                fee_vault: vault,
+                end_timestamp,
+            }
+            .instantiate()
+            .prepare_to_globalize(OwnerRole::Fixed(rule!(require(owner_badge.resource_address()))))
+            .roles(roles!(
+                operator => operator_rule.unwrap_or(rule!(deny_all));
+            ))
+            .with_address(address_reservation)
+            .globalize();
+
+            (component, owner_badge)
+        }
+
+        // The owner withdraws the deposit. This stops the component from working.
+        pub fn withdraw_deposit(&mut self) -> FungibleBucket {
+            assert!(self.deposit_vault.amount() > Decimal::ZERO, "Nothing to withdraw");
+
+            self.deposit_vault.take_all()
+        }
+
+        // Withdraws funds from the vault, if a fee is asked.
+        pub fn withdraw_fees(&mut self) -> FungibleBucket {
+            match self.fee_vault {
+                Some(ref mut fee_vault) => {
+                    assert!(fee_vault.amount() > Decimal::ZERO, "Nothing to withdraw");
+
+                    fee_vault.take_all().as_fungible()
+                }
+                None => panic!("This component does not ask for a fee!"),
+            }
+        }
+
+        // Updates the fee per unit proven
+        pub fn update_fee(&mut self, amount: Decimal) {
+            match self.fee_info {
+                Some(ref mut fee_info) => fee_info.amount = amount,
+                None => panic!("This component does not ask for a fee!"),
+            }
+        }
+
+        // Updates the maximum amount a single Proof can be generated for
+        pub fn update_max_amount(&mut self, max_amount: Decimal) {
+            assert!(max_amount > Decimal::ZERO, "Max amount must be higher than 0");
+
+            self.max_amount = max_amount;
+        }
+
+        // Updates the end timestamp
+        pub fn update_end_timestamp(&mut self, new_timestamp: Instant) {
+            // Cannot set a timestamp in the past
+            assert!(
+                new_timestamp > Clock::current_time_rounded_to_seconds(),
+                "Timestamp must be greater than the current time"
+            );
+
+            self.end_timestamp = new_timestamp;
+        }
+
+        // Generates a proof of the requested amount of the deposit and returns it with any
+        // remainder of the payment, if any payment was provided.
+        pub fn get_proof(
+            &mut self,
+            amount: Decimal,
+            mut payment: Option<Bucket>,
+        ) -> (FungibleProof, Option<Bucket>) {
+            assert!(amount > Decimal::ZERO, "Amount must be higher than 0");
+            assert!(amount <= self.max_amount, "Amount is higher than the maximum amount");
+            assert!(
+                amount <= self.deposit_vault.amount(),
+                "This component does not hold enough to prove this amount."
+            );
+            assert!(
+                Clock::current_time_rounded_to_seconds() < self.end_timestamp,
+                "You can no longer get a proof of this deposit."
+            );
+
+            let proof = self.deposit_vault.create_proof_of_amount(amount);
+
+            // If a payment is required, the fee is the price per unit times the amount proven
+            if let Some(ref fee_info) = self.fee_info {
+                let fee = fee_info.amount * amount;
+
+                // Make sure a payment was provided
+                let payment = payment.as_mut().expect("No payment was provided");
+                // Make sure it was the correct resource
+                assert!(
+                    payment.resource_address() == fee_info.resource,
+                    "Did not pay with correct resource!"
+                );
+                // Make sure it was the correct amount
+                assert!(payment.amount() >= fee, "Did not pay enough!");
+
+                // Take the payment, rounded up to the divisibility of the fee resource
+                self.fee_vault.as_mut().unwrap().put(payment.take_advanced(
+                    fee,
+                    WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+                ));
+            }
+
+            // Return proof and either a payment remainder or None
+            (proof, payment)
+        }
+    }
+}
@@ -1,27 +1,118 @@
+use scrypto::engine::scrypto_env::ScryptoVmV1Api;
 use scrypto::prelude::*;
-use types::FeeInfo;
+use types::{
+    AccessCode, AccessGrant, AccessRequest, AllowedCaller, FeeInfo, FeePayment, FlashLoanReceipt, HoldingRequirement,
+    InstantiateOptions, IssuanceKind, IssuanceReceipt, IssuanceRecord, NftInfo, OraclePricing, OwnerBadgeData,
+    ProofTicket, RecoveryInfo, RequestStatus, RiskFlags, RiskPolicy, SettlementInfo, SignedPermit, SwapHook,
+    TargetMethod, TicketPayment, TransactionPolicy, UsageStats,
+};
 
+pub mod fungible_flash_proof;
 pub mod types;
 
 #[blueprint]
 mod flash_proof {
     enable_method_auth! {
+        roles {
+            operator => updatable_by: [OWNER];
+            recovery => updatable_by: [OWNER];
+        },
         methods {
             withdraw_nft => restrict_to: [OWNER];
+            deposit_nfts => restrict_to: [OWNER];
             withdraw_fees => restrict_to: [OWNER];
-            update_fee => restrict_to: [OWNER];
-            update_end_timestamp => restrict_to: [OWNER];
+            withdraw_tickets => restrict_to: [OWNER];
+            update_fee => restrict_to: [operator, OWNER];
+            update_bundle_fee => restrict_to: [operator, OWNER];
+            update_transaction_policy => restrict_to: [operator, OWNER];
+            update_oracle_pricing => restrict_to: [operator, OWNER];
+            update_swap_hook => restrict_to: [operator, OWNER];
+            update_ticket_payment => restrict_to: [operator, OWNER];
+            update_holding_requirement => restrict_to: [operator, OWNER];
+            update_end_timestamp => restrict_to: [operator, OWNER];
+            add_allowed_caller => restrict_to: [operator, OWNER];
+            remove_allowed_caller => restrict_to: [operator, OWNER];
+            add_allowed_target => restrict_to: [operator, OWNER];
+            remove_allowed_target => restrict_to: [operator, OWNER];
+            update_owner_badge_label => restrict_to: [OWNER];
+            update_permit_key => restrict_to: [OWNER];
+            mint_grant => restrict_to: [OWNER];
+            revoke_grant => restrict_to: [OWNER];
+            update_approval_window => restrict_to: [OWNER];
+            approve_request => restrict_to: [OWNER];
+            reject_request => restrict_to: [OWNER];
+            add_access_code => restrict_to: [operator, OWNER];
+            remove_access_code => restrict_to: [operator, OWNER];
+            acknowledge_nft_data_change => restrict_to: [OWNER];
+            initiate_recovery => restrict_to: [recovery];
+            cancel_recovery => restrict_to: [OWNER];
+            complete_recovery => restrict_to: [recovery];
+            heartbeat => restrict_to: [OWNER];
+            get_last_heartbeat => PUBLIC;
+            get_usage_stats => PUBLIC;
+            get_fees_collected => PUBLIC;
+            get_issuance_log => PUBLIC;
+            get_nft_data => PUBLIC;
             get_nft_proof => PUBLIC;
+            get_nft_proof_with_receipt => PUBLIC;
+            get_nft_proofs => PUBLIC;
+            get_collection_proof => PUBLIC;
+            call_with_proof => PUBLIC;
+            get_nft_proof_with_permit => PUBLIC;
+            get_nft_proof_with_code => PUBLIC;
+            get_nft_proof_with_grant => PUBLIC;
+            request_access => PUBLIC;
+            redeem_request => PUBLIC;
+            reclaim_request => PUBLIC;
+            flash_borrow_nft => PUBLIC;
+            flash_repay_nft => PUBLIC;
+            settle => PUBLIC;
         }
     }
 
     struct FlashProof {
         owner_resource: ResourceAddress,
+        owner_badge_id: Option<NonFungibleLocalId>,
         nft_vault: NonFungibleVault,
         nft_id: NonFungibleGlobalId,
+        risk_flags: RiskFlags,
+        guard_nft_data: bool,
+        nft_data_hashes: KeyValueStore<NonFungibleLocalId, Hash>,
         fee_info: Option<FeeInfo>,
         fee_vault: Option<Vault>,
+        bundle_fee: Option<Decimal>,
+        oracle_pricing: Option<OraclePricing>,
+        swap_hook: Option<SwapHook>,
+        ticket_payment: Option<TicketPayment>,
+        ticket_vaults: KeyValueStore<ResourceAddress, Vault>,
+        ticket_resources: IndexSet<ResourceAddress>,
+        transaction_policy: TransactionPolicy,
+        last_paid_transaction: Option<Hash>,
+        last_issuance_transaction: Option<Hash>,
+        collection_cursor: u64,
+        flash_receipt_resource: ResourceAddress,
         end_timestamp: Instant,
+        allowed_callers: IndexSet<AllowedCaller>,
+        allowed_targets: IndexSet<TargetMethod>,
+        holding_requirement: Option<HoldingRequirement>,
+        permit_key: Option<Bls12381G1PublicKey>,
+        permit_uses: KeyValueStore<u64, u32>,
+        access_codes: KeyValueStore<Hash, AccessCode>,
+        access_code_uses: KeyValueStore<Hash, u32>,
+        grant_resource: ResourceAddress,
+        revoked_grants: KeyValueStore<NonFungibleLocalId, ()>,
+        approval_window: Option<i64>,
+        request_resource: ResourceAddress,
+        request_escrows: KeyValueStore<NonFungibleLocalId, Vault>,
+        issuance_receipt_resource: ResourceAddress,
+        usage_stats: UsageStats,
+        fees_collected: KeyValueStore<ResourceAddress, Decimal>,
+        issuance_log: KeyValueStore<u64, IssuanceRecord>,
+        recovery_delay: Option<i64>,
+        recovery_ready_at: Option<Instant>,
+        settlement: Option<SettlementInfo>,
+        heartbeat_interval: Option<i64>,
+        last_heartbeat: Instant,
     }
 
     impl FlashProof {
@@ -30,6 +121,43 @@ mod flash_proof {
             fee_info: Option<FeeInfo>,
             end_timestamp: Instant,
         ) -> (Global<FlashProof>, FungibleBucket) {
+            let (component, owner_badge) =
+                Self::instantiate_with_options(nft, fee_info, end_timestamp, InstantiateOptions::default());
+
+            (component, owner_badge.as_fungible())
+        }
+
+        // Creates a non-fungible owner badge resource which can be shared by all components
+        // instantiated from this package. Only this package is able to mint badges or update their label.
+        pub fn create_owner_badge_resource() -> ResourceAddress {
+            let package_rule = rule!(require(package_of_direct_caller(Runtime::package_address())));
+
+            ResourceBuilder::new_ruid_non_fungible::<OwnerBadgeData>(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Flash Proof component owner", locked;
+                        "description" => "Owner badges for Flash Proof components. Can be used to update state on the component, withdraw your NFT and claim fees.", locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => package_rule.clone();
+                    minter_updater => rule!(deny_all);
+                ))
+                .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                    non_fungible_data_updater => package_rule;
+                    non_fungible_data_updater_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply()
+                .address()
+        }
+
+        // Instantiates a component with additional, optional settings
+        pub fn instantiate_with_options(
+            nft: NonFungibleBucket,
+            fee_info: Option<FeeInfo>,
+            end_timestamp: Instant,
+            options: InstantiateOptions,
+        ) -> (Global<FlashProof>, Bucket) {
             // Get an address reservation which we'll use in the description of the owner resource
             let (address_reservation, component_address) = Runtime::allocate_component_address(FlashProof::blueprint_id());
 
@@ -46,6 +174,23 @@ mod flash_proof {
                 vault = Some(Vault::new(fee_info.resource));
             };
 
+            // A keeper reward is paid from the fees, so it requires a fee
+            if let Some(SettlementInfo { keeper_reward: Some(keeper_reward), .. }) = &options.settlement {
+                assert!(vault.is_some(), "A keeper reward requires a fee");
+                assert!(
+                    *keeper_reward >= Decimal::ZERO,
+                    "Keeper reward cannot be negative"
+                );
+            }
+
+            if let Some(interval) = options.heartbeat_interval {
+                assert!(interval > 0, "Heartbeat interval must be higher than 0");
+            }
+
+            if let Some(window) = options.approval_window {
+                assert!(window > 0, "Approval window must be higher than 0");
+            }
+
             // Get the resource address and NonFungibleLocalId
             assert!(nft.amount() == Decimal::ONE, "Must supply exactly 1 NFT!");
             let nft_resource = nft.resource_address();
@@ -57,37 +202,261 @@ mod flash_proof {
                 nflid.clone()
             );
 
+            // If the issuer can recall or freeze the NFT, proof generation can be disrupted
+            let risk_flags = Self::inspect_nft_resource(nft_resource);
+            if options.risk_policy == RiskPolicy::Reject {
+                assert!(!risk_flags.recallable, "NFT resource is recallable");
+                assert!(!risk_flags.freezable, "NFT resource is freezable");
+            }
+
+            // Snapshot the NFT data, so proof generation can stop when it changes
+            let nft_data_hashes = KeyValueStore::new();
+            if options.guard_nft_data {
+                nft_data_hashes.insert(nflid.clone(), Self::non_fungible_data_hash(nft_resource, &nflid));
+            }
+
             // Create an owner badge
-            let owner_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+            let (owner_badge, owner_requirement, owner_badge_id) =
+                Self::create_owner_badge(&nft_id, component_address, options.owner_badge_resource);
+
+            // Create the receipt resource for flash loans of the NFT. Receipts cannot be deposited,
+            // so the NFT has to be repaid in the same transaction for the receipt to be burned.
+            let component_rule = rule!(require(global_caller(component_address)));
+            let flash_receipt_resource = ResourceBuilder::new_ruid_non_fungible::<FlashLoanReceipt>(OwnerRole::None)
                 .metadata(metadata!(
                     init {
-                        "symbol" => "FLASHOWN", locked;
-                        "name" => "Flash Proof component owner", locked;
-                        "description" => "The owner badge for a Flash Proof component. Can be used to update state on the component, withdraw your NFT and claim fees.", locked;
-                        "nft" => nft_id.clone(), locked;
+                        "name" => "Flash Proof loan receipt", locked;
+                        "component" => GlobalAddress::from(component_address), locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => component_rule.clone();
+                    minter_updater => rule!(deny_all);
+                ))
+                .burn_roles(burn_roles!(
+                    burner => component_rule.clone();
+                    burner_updater => rule!(deny_all);
+                ))
+                .deposit_roles(deposit_roles!(
+                    depositor => rule!(deny_all);
+                    depositor_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply()
+                .address();
+
+            // Create the resource for access grants. The owner can recall grants, the component
+            // keeps the recaller role pointed at the current owner badge.
+            let grant_resource = ResourceBuilder::new_ruid_non_fungible::<AccessGrant>(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Flash Proof access grant", locked;
                         "component" => GlobalAddress::from(component_address), locked;
                     }
                 ))
-                .divisibility(DIVISIBILITY_NONE)
-                .mint_initial_supply(1);
+                .mint_roles(mint_roles!(
+                    minter => component_rule.clone();
+                    minter_updater => rule!(deny_all);
+                ))
+                .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                    non_fungible_data_updater => component_rule.clone();
+                    non_fungible_data_updater_updater => rule!(deny_all);
+                ))
+                .recall_roles(recall_roles!(
+                    recaller => rule!(require(owner_requirement.clone()));
+                    recaller_updater => component_rule.clone();
+                ))
+                .create_with_no_initial_supply()
+                .address();
+
+            // Create the resource for access request receipts, which the component updates on approval
+            // or rejection and burns when the request is redeemed or reclaimed
+            let request_resource = ResourceBuilder::new_ruid_non_fungible::<AccessRequest>(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Flash Proof access request", locked;
+                        "component" => GlobalAddress::from(component_address), locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => component_rule.clone();
+                    minter_updater => rule!(deny_all);
+                ))
+                .burn_roles(burn_roles!(
+                    burner => component_rule.clone();
+                    burner_updater => rule!(deny_all);
+                ))
+                .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                    non_fungible_data_updater => component_rule.clone();
+                    non_fungible_data_updater_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply()
+                .address();
+
+            // Create the resource for issuance receipts. Receipts cannot be withdrawn once deposited,
+            // so they stay with the borrower as evidence of the issuance.
+            let issuance_receipt_resource = ResourceBuilder::new_ruid_non_fungible::<IssuanceReceipt>(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Flash Proof issuance receipt", locked;
+                        "component" => GlobalAddress::from(component_address), locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => component_rule;
+                    minter_updater => rule!(deny_all);
+                ))
+                .withdraw_roles(withdraw_roles!(
+                    withdrawer => rule!(deny_all);
+                    withdrawer_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply()
+                .address();
+
+            // Without an operator, only the owner can manage the component
+            let operator_rule = options.operator_rule.unwrap_or(rule!(deny_all));
+
+            // If recovery is possible, the component must be able to re-point the owner role to a new badge itself
+            let (owner_role, recovery_rule, recovery_delay) = match options.recovery {
+                Some(RecoveryInfo { rule, delay }) => {
+                    assert!(delay > 0, "Recovery delay must be higher than 0");
+
+                    (
+                        OwnerRole::Updatable(Self::recoverable_owner_rule(owner_requirement, component_address)),
+                        rule,
+                        Some(delay),
+                    )
+                }
+                None => (OwnerRole::Fixed(rule!(require(owner_requirement))), rule!(deny_all), None),
+            };
 
             // Instantiate the component and make the supplied owner resource address the owner
             let component = Self {
                 owner_resource: owner_badge.resource_address(),
+                owner_badge_id,
                 nft_vault: NonFungibleVault::with_bucket(nft),
                 nft_id,
+                risk_flags: risk_flags.clone(),
+                guard_nft_data: options.guard_nft_data,
+                nft_data_hashes,
                 fee_info,
                 fee_vault: vault,
+                bundle_fee: None,
+                oracle_pricing: None,
+                swap_hook: None,
+                ticket_payment: None,
+                ticket_vaults: KeyValueStore::new(),
+                ticket_resources: IndexSet::new(),
+                transaction_policy: options.transaction_policy,
+                last_paid_transaction: None,
+                last_issuance_transaction: None,
+                collection_cursor: 0,
+                flash_receipt_resource,
                 end_timestamp,
+                allowed_callers: IndexSet::new(),
+                allowed_targets: IndexSet::new(),
+                holding_requirement: None,
+                permit_key: None,
+                permit_uses: KeyValueStore::new(),
+                access_codes: KeyValueStore::new(),
+                access_code_uses: KeyValueStore::new(),
+                grant_resource,
+                revoked_grants: KeyValueStore::new(),
+                approval_window: options.approval_window,
+                request_resource,
+                request_escrows: KeyValueStore::new(),
+                issuance_receipt_resource,
+                usage_stats: UsageStats::default(),
+                fees_collected: KeyValueStore::new(),
+                issuance_log: KeyValueStore::new(),
+                recovery_delay,
+                recovery_ready_at: None,
+                settlement: options.settlement,
+                heartbeat_interval: options.heartbeat_interval,
+                last_heartbeat: Clock::current_time_rounded_to_seconds(),
             }
             .instantiate()
-            .prepare_to_globalize(OwnerRole::Fixed(rule!(require(owner_badge.resource_address()))))
+            .prepare_to_globalize(owner_role)
+            .roles(roles!(
+                operator => operator_rule;
+                recovery => recovery_rule;
+            ))
+            .metadata(metadata!(
+                init {
+                    "risk_flags" => risk_flags.to_strings(), locked;
+                }
+            ))
             .with_address(address_reservation)
             .globalize();
 
             (component, owner_badge)
         }
 
+        // Creates the owner badge: either a fungible badge of its own resource, or a non-fungible
+        // badge minted from a shared owner badge resource. Also returns what the owner role should require.
+        fn create_owner_badge(
+            nft_id: &NonFungibleGlobalId,
+            component_address: ComponentAddress,
+            badge_resource: Option<ResourceAddress>,
+        ) -> (Bucket, ResourceOrNonFungible, Option<NonFungibleLocalId>) {
+            if let Some(badge_resource) = badge_resource {
+                assert!(!badge_resource.is_fungible(), "Owner badge resource must be non-fungible");
+
+                let badge = ResourceManager::from(badge_resource).mint_ruid_non_fungible(
+                    OwnerBadgeData {
+                        component: component_address,
+                        nft_id: nft_id.clone(),
+                        created_at: Clock::current_time_rounded_to_seconds(),
+                        label: String::new(),
+                    }
+                );
+                let badge_id = badge.as_non_fungible().non_fungible_local_id();
+                let requirement = ResourceOrNonFungible::NonFungible(
+                    NonFungibleGlobalId::new(badge_resource, badge_id.clone())
+                );
+
+                (badge, requirement, Some(badge_id))
+            } else {
+                let badge = ResourceBuilder::new_fungible(OwnerRole::None)
+                    .metadata(metadata!(
+                        init {
+                            "symbol" => "FLASHOWN", locked;
+                            "name" => "Flash Proof component owner", locked;
+                            "description" => "The owner badge for a Flash Proof component. Can be used to update state on the component, withdraw your NFT and claim fees.", locked;
+                            "nft" => nft_id.clone(), locked;
+                            "component" => GlobalAddress::from(component_address), locked;
+                        }
+                    ))
+                    .divisibility(DIVISIBILITY_NONE)
+                    .mint_initial_supply(1);
+                let requirement = ResourceOrNonFungible::Resource(badge.resource_address());
+
+                (badge.into(), requirement, None)
+            }
+        }
+
+        // Checks whether the issuer of the NFT is, or can become, able to recall or freeze it
+        fn inspect_nft_resource(nft_resource: ResourceAddress) -> RiskFlags {
+            let resource_manager = ResourceManager::from(nft_resource);
+            let is_set = |roles: [&str; 2]| {
+                roles.iter().any(|role| {
+                    !matches!(resource_manager.get_role(role), None | Some(AccessRule::DenyAll))
+                })
+            };
+
+            RiskFlags {
+                recallable: is_set([RECALLER_ROLE, RECALLER_UPDATER_ROLE]),
+                freezable: is_set([FREEZER_ROLE, FREEZER_UPDATER_ROLE]),
+            }
+        }
+
+        // The owner rule of a recoverable component, which also allows the component itself to update it
+        fn recoverable_owner_rule(
+            owner_requirement: ResourceOrNonFungible,
+            component_address: ComponentAddress,
+        ) -> AccessRule {
+            rule!(require(owner_requirement) || require(global_caller(component_address)))
+        }
+
         // The owner withdraws the NFT. This stops the component from working.
         pub fn withdraw_nft(&mut self) -> NonFungibleBucket {
             assert!(self.nft_vault.amount() > dec!(0), "Nothing to withdraw");
@@ -95,20 +464,43 @@ mod flash_proof {
             self.nft_vault.take_all()
         }
 
+        // The owner deposits more NFTs of the same resource, so they can be proven together
+        pub fn deposit_nfts(&mut self, nfts: NonFungibleBucket) {
+            assert!(
+                nfts.resource_address() == self.nft_id.resource_address(),
+                "Can only deposit NFTs of the same resource"
+            );
+
+            if self.guard_nft_data {
+                for id in nfts.non_fungible_local_ids() {
+                    let hash = Self::non_fungible_data_hash(nfts.resource_address(), &id);
+                    self.nft_data_hashes.insert(id, hash);
+                }
+            }
+
+            self.nft_vault.put(nfts);
+        }
+
         // Withdraws funds from the vault, if a fee is asked.
         pub fn withdraw_fees(&mut self) -> FungibleBucket {
-            if self.fee_vault.is_some() {
-                assert!(
-                    self.fee_vault.as_mut().unwrap().amount() > Decimal::ZERO,
-                    "Nothing to withdraw"
-                );
-    
-                self.fee_vault.as_mut().unwrap().take_all().as_fungible()
-            } else {
-                panic!("This component does not ask for a fee!");
+            match self.fee_vault {
+                Some(ref mut fee_vault) => {
+                    assert!(fee_vault.amount() > Decimal::ZERO, "Nothing to withdraw");
+
+                    fee_vault.take_all().as_fungible()
+                }
+                None => panic!("This component does not ask for a fee!"),
             }
         }
 
+        // Withdraws the tickets of a resource that were kept as payment
+        pub fn withdraw_tickets(&mut self, resource: ResourceAddress) -> NonFungibleBucket {
+            let mut ticket_vault = self.ticket_vaults.get_mut(&resource).expect("No tickets of this resource");
+            assert!(ticket_vault.amount() > Decimal::ZERO, "Nothing to withdraw");
+
+            ticket_vault.take_all().as_non_fungible()
+        }
+
         // Updates the fee
         pub fn update_fee(&mut self, amount: Decimal) {
             if self.fee_vault.is_some() {
@@ -118,6 +510,67 @@ mod flash_proof {
             }
         }
 
+        // Sets a single price for proving several NFTs at once. Without it, every NFT is charged the fee.
+        pub fn update_bundle_fee(&mut self, bundle_fee: Option<Decimal>) {
+            assert!(self.fee_vault.is_some(), "This component does not ask for a fee!");
+            if let Some(bundle_fee) = bundle_fee {
+                assert!(bundle_fee >= Decimal::ZERO, "Bundle fee cannot be negative");
+            }
+
+            self.bundle_fee = bundle_fee;
+        }
+
+        // Denominates the fee per NFT in a reference unit, converted into the fee resource at
+        // the price of an oracle, or goes back to the fixed fee by setting it to None
+        pub fn update_oracle_pricing(&mut self, oracle_pricing: Option<OraclePricing>) {
+            assert!(self.fee_vault.is_some(), "This component does not ask for a fee!");
+            if let Some(ref pricing) = oracle_pricing {
+                assert!(
+                    pricing.reference_amount > Decimal::ZERO,
+                    "Reference amount must be higher than 0"
+                );
+                assert!(pricing.max_age > 0, "Max age must be higher than 0");
+                if let Some(fallback_price) = pricing.fallback_price {
+                    assert!(fallback_price > Decimal::ZERO, "Fallback price must be higher than 0");
+                }
+            }
+
+            self.oracle_pricing = oracle_pricing;
+        }
+
+        // Sets a swap component that converts payments in other resources into the fee resource,
+        // or stops accepting other resources by setting it to None
+        pub fn update_swap_hook(&mut self, swap_hook: Option<SwapHook>) {
+            assert!(self.fee_vault.is_some(), "This component does not ask for a fee!");
+            if let Some(ref swap_hook) = swap_hook {
+                assert!(
+                    swap_hook.max_slippage >= Decimal::ZERO && swap_hook.max_slippage < Decimal::ONE,
+                    "Max slippage must be at least 0 and lower than 1"
+                );
+            }
+
+            self.swap_hook = swap_hook;
+        }
+
+        // Accepts non-fungible tickets of a resource as payment, or stops accepting them by setting it
+        // to None. Without a fee, a ticket is required for every proof.
+        pub fn update_ticket_payment(&mut self, ticket_payment: Option<TicketPayment>) {
+            if let Some(ref ticket_payment) = ticket_payment {
+                assert!(!ticket_payment.resource.is_fungible(), "Ticket resource must be non-fungible");
+                assert!(
+                    self.approval_window.is_none(),
+                    "Access requests can't be paid with tickets"
+                );
+            }
+
+            self.ticket_payment = ticket_payment;
+        }
+
+        // Updates what happens when proofs are requested more than once in a transaction
+        pub fn update_transaction_policy(&mut self, transaction_policy: TransactionPolicy) {
+            self.transaction_policy = transaction_policy;
+        }
+
         // Updates the end timestamp
         pub fn update_end_timestamp(&mut self, new_timestamp: Instant) {
             // Cannot set a timestamp in the past
@@ -129,43 +582,1042 @@ mod flash_proof {
             self.end_timestamp = new_timestamp;
         }
 
+        // Requires borrowers to hold an amount of a token, or removes that requirement.
+        // Holders can optionally be exempted from the fee.
+        pub fn update_holding_requirement(&mut self, holding_requirement: Option<HoldingRequirement>) {
+            if let Some(ref holding_requirement) = holding_requirement {
+                assert!(
+                    holding_requirement.resource.is_fungible(),
+                    "Holding requirement resource must be fungible"
+                );
+                assert!(
+                    holding_requirement.amount > Decimal::ZERO,
+                    "Holding requirement amount must be higher than 0"
+                );
+            }
+
+            self.holding_requirement = holding_requirement;
+        }
+
+        // Only lets the given package, blueprint or component obtain proofs, along with any other
+        // allowed callers. Calls straight from a transaction manifest are no longer allowed.
+        pub fn add_allowed_caller(&mut self, caller: AllowedCaller) {
+            assert!(self.allowed_callers.insert(caller), "Caller is already allowed");
+        }
+
+        // Removes an allowed caller. Once no callers are left, anyone can obtain proofs again.
+        pub fn remove_allowed_caller(&mut self, caller: AllowedCaller) {
+            assert!(self.allowed_callers.shift_remove(&caller), "Caller is not allowed");
+        }
+
+        // Allows call_with_proof to call the given method with a proof of the NFT
+        pub fn add_allowed_target(&mut self, target: TargetMethod) {
+            assert!(self.allowed_targets.insert(target), "Target method is already allowed");
+        }
+
+        // Removes a target method from the ones call_with_proof can call
+        pub fn remove_allowed_target(&mut self, target: TargetMethod) {
+            assert!(self.allowed_targets.shift_remove(&target), "Target method is not allowed");
+        }
+
+        // Updates the label on a non-fungible owner badge
+        pub fn update_owner_badge_label(&mut self, label: String) {
+            match self.owner_badge_id {
+                Some(ref badge_id) => {
+                    ResourceManager::from(self.owner_resource)
+                        .update_non_fungible_data(badge_id, "label", label);
+                }
+                None => panic!("This component does not have a non-fungible owner badge!"),
+            }
+        }
+
+        // The owner accepts the current data of the held NFTs, which resumes proof generation
+        // after the data was changed
+        pub fn acknowledge_nft_data_change(&mut self) {
+            assert!(self.guard_nft_data, "This component does not guard the NFT data!");
+
+            let nft_resource = self.nft_id.resource_address();
+            for id in self.nft_vault.non_fungible_local_ids(u32::MAX) {
+                let hash = Self::non_fungible_data_hash(nft_resource, &id);
+                self.nft_data_hashes.insert(id, hash);
+            }
+        }
+
+        // Sets the public key permits must be signed with, or stops accepting permits
+        pub fn update_permit_key(&mut self, permit_key: Option<Bls12381G1PublicKey>) {
+            self.permit_key = permit_key;
+        }
+
+        // Adds a code that unlocks proofs, identified by the keccak256 hash of the code.
+        // Adding a code again resets its uses.
+        pub fn add_access_code(&mut self, code_hash: Hash, access_code: AccessCode) {
+            assert!(access_code.max_uses > 0, "Max uses must be higher than 0");
+            assert!(
+                access_code.expires_at > Clock::current_time_rounded_to_seconds(),
+                "Expiry must be greater than the current time"
+            );
+
+            self.access_codes.insert(code_hash, access_code);
+            self.access_code_uses.insert(code_hash, 0);
+        }
+
+        // Removes a code, so it no longer unlocks proofs
+        pub fn remove_access_code(&mut self, code_hash: Hash) {
+            assert!(self.access_codes.get(&code_hash).is_some(), "Unknown access code");
+
+            self.access_codes.remove(&code_hash);
+        }
+
+        // Mints a grant badge for a partner, which can be used to get proofs until it expires,
+        // is used up or is revoked. A fee override replaces the regular fee for the grant.
+        pub fn mint_grant(
+            &mut self,
+            expires_at: Instant,
+            uses: u32,
+            fee_override: Option<Decimal>,
+        ) -> NonFungibleBucket {
+            assert!(uses > 0, "Uses must be higher than 0");
+            assert!(
+                expires_at > Clock::current_time_rounded_to_seconds(),
+                "Expiry must be greater than the current time"
+            );
+            if let Some(fee_override) = fee_override {
+                assert!(fee_override >= Decimal::ZERO, "Fee override cannot be negative");
+            }
+
+            ResourceManager::from(self.grant_resource)
+                .mint_ruid_non_fungible(AccessGrant {
+                    expires_at,
+                    remaining_uses: uses,
+                    fee_override,
+                })
+                .as_non_fungible()
+        }
+
+        // Revokes a grant, so it can no longer be used to get proofs
+        pub fn revoke_grant(&mut self, id: NonFungibleLocalId) {
+            assert!(
+                ResourceManager::from(self.grant_resource).non_fungible_exists(&id),
+                "Unknown grant"
+            );
+
+            self.revoked_grants.insert(id, ());
+        }
+
+        // Sets how long an approved access request can be redeemed. When set, borrowers must request
+        // access and be approved, and proofs can no longer be generated without approval.
+        pub fn update_approval_window(&mut self, approval_window: Option<i64>) {
+            if let Some(window) = approval_window {
+                assert!(window > 0, "Approval window must be higher than 0");
+                // Access requests escrow the fee, they can't take tickets
+                assert!(
+                    self.ticket_payment.is_none(),
+                    "Access requests can't be paid with tickets"
+                );
+            }
+
+            self.approval_window = approval_window;
+        }
+
+        // Approves a pending access request, which can then be redeemed within the approval window
+        pub fn approve_request(&mut self, id: NonFungibleLocalId) {
+            let window = self.approval_window.expect("This component does not take access requests!");
+            self.assert_request_pending(&id);
+
+            let redeem_before = Clock::current_time_rounded_to_seconds()
+                .add_seconds(window)
+                .unwrap();
+            ResourceManager::from(self.request_resource)
+                .update_non_fungible_data(&id, "status", RequestStatus::Approved { redeem_before });
+        }
+
+        // Rejects a pending access request. The borrower can reclaim the escrowed fee.
+        pub fn reject_request(&mut self, id: NonFungibleLocalId) {
+            self.assert_request_pending(&id);
+
+            ResourceManager::from(self.request_resource)
+                .update_non_fungible_data(&id, "status", RequestStatus::Rejected);
+        }
+
+        // Starts the recovery of the owner badge. It can be completed after the recovery delay,
+        // unless the owner cancels it in the meantime.
+        pub fn initiate_recovery(&mut self) {
+            let delay = self.recovery_delay.expect("This component does not support recovery!");
+            assert!(self.recovery_ready_at.is_none(), "A recovery is already pending");
+
+            self.recovery_ready_at = Some(
+                Clock::current_time_rounded_to_seconds()
+                    .add_seconds(delay)
+                    .unwrap()
+            );
+        }
+
+        // The owner cancels a pending recovery
+        pub fn cancel_recovery(&mut self) {
+            assert!(self.recovery_ready_at.is_some(), "No recovery is pending");
+
+            self.recovery_ready_at = None;
+        }
+
+        // Completes a pending recovery by creating a new owner badge and making it the owner
+        pub fn complete_recovery(&mut self) -> Bucket {
+            let ready_at = self.recovery_ready_at.expect("No recovery is pending");
+            assert!(
+                Clock::current_time_rounded_to_seconds() >= ready_at,
+                "The recovery delay has not passed yet"
+            );
+
+            // A non-fungible owner badge is replaced by a new one from the same resource
+            let component_address = Runtime::global_address();
+            let badge_resource = self.owner_badge_id.as_ref().map(|_| self.owner_resource);
+            let (owner_badge, owner_requirement, owner_badge_id) =
+                Self::create_owner_badge(&self.nft_id, component_address, badge_resource);
+
+            ResourceManager::from(self.grant_resource)
+                .set_role(RECALLER_ROLE, rule!(require(owner_requirement.clone())));
+            Runtime::global_component()
+                .set_owner_role(Self::recoverable_owner_rule(owner_requirement, component_address));
+
+            self.owner_resource = owner_badge.resource_address();
+            self.owner_badge_id = owner_badge_id;
+            self.recovery_ready_at = None;
+
+            owner_badge
+        }
+
+        // After the end timestamp, anyone can return the NFT, the fees and the kept tickets to the
+        // return account.
+        // The caller receives the keeper reward, if one is set.
+        pub fn settle(&mut self) -> Option<Bucket> {
+            let settlement = self.settlement.clone().expect("This component has no return account!");
+            assert!(
+                Clock::current_time_rounded_to_seconds() >= self.end_timestamp,
+                "This component has not expired yet."
+            );
+            assert!(self.nft_vault.amount() > Decimal::ZERO, "Nothing to settle");
+
+            // The NFT must be accepted by the return account, otherwise the owner has to withdraw it
+            let refund = Global::<Account>::from(settlement.account)
+                .try_deposit_or_refund(self.nft_vault.take_all().into(), None);
+            assert!(refund.is_none(), "The return account does not accept the NFT");
+
+            // Pay the keeper reward and return the rest of the fees. Fees that are refused stay in the component.
+            let mut reward = None;
+            if let Some(ref mut fee_vault) = self.fee_vault {
+                if let Some(keeper_reward) = settlement.keeper_reward {
+                    reward = Some(fee_vault.take(keeper_reward.min(fee_vault.amount())));
+                }
+
+                if fee_vault.amount() > Decimal::ZERO {
+                    let refund = Global::<Account>::from(settlement.account)
+                        .try_deposit_or_refund(fee_vault.take_all(), None);
+                    if let Some(fees) = refund {
+                        fee_vault.put(fees);
+                    }
+                }
+            }
+
+            // Return the kept tickets as well. Tickets that are refused stay in the component.
+            for resource in self.ticket_resources.iter() {
+                let mut ticket_vault = self.ticket_vaults.get_mut(resource).unwrap();
+                if ticket_vault.amount() > Decimal::ZERO {
+                    let refund = Global::<Account>::from(settlement.account)
+                        .try_deposit_or_refund(ticket_vault.take_all(), None);
+                    if let Some(tickets) = refund {
+                        ticket_vault.put(tickets);
+                    }
+                }
+            }
+
+            reward
+        }
+
+        // The owner signals they are still around. Required regularly if a heartbeat interval is set.
+        pub fn heartbeat(&mut self) {
+            self.last_heartbeat = Clock::current_time_rounded_to_seconds();
+        }
+
+        // Returns when the owner last called heartbeat
+        pub fn get_last_heartbeat(&self) -> Instant {
+            self.last_heartbeat
+        }
+
+        // Returns the number of proofs issued and when the first and last were issued
+        pub fn get_usage_stats(&self) -> UsageStats {
+            self.usage_stats.clone()
+        }
+
+        // Returns the total fees collected in the given resource
+        pub fn get_fees_collected(&self, resource: ResourceAddress) -> Decimal {
+            self.fees_collected.get(&resource).map(|amount| *amount).unwrap_or(Decimal::ZERO)
+        }
+
+        // Returns up to limit entries of the issuance log, starting at the given index
+        pub fn get_issuance_log(&self, start: u64, limit: u32) -> Vec<IssuanceRecord> {
+            let end = self.usage_stats.total_proofs.min(start.saturating_add(limit as u64));
+
+            (start..end)
+                .filter_map(|index| self.issuance_log.get(&index).map(|record| record.clone()))
+                .collect()
+        }
+
+        // Returns the raw SBOR encoded data of a held NFT, the primary NFT by default,
+        // together with a summary of the metadata of its resource
+        pub fn get_nft_data(&self, id: Option<NonFungibleLocalId>) -> NftInfo {
+            let id = id.unwrap_or_else(|| self.nft_id.local_id().clone());
+            assert!(
+                self.nft_vault.contains_non_fungible(&id),
+                "This component does not hold the requested NFT."
+            );
+
+            let nft_resource = self.nft_id.resource_address();
+            let resource_manager = ResourceManager::from(nft_resource);
+
+            NftInfo {
+                data: Self::non_fungible_data(nft_resource, &id),
+                id: NonFungibleGlobalId::new(nft_resource, id),
+                name: resource_manager.get_metadata("name").ok().flatten(),
+                description: resource_manager.get_metadata("description").ok().flatten(),
+                icon_url: resource_manager.get_metadata("icon_url").ok().flatten(),
+            }
+        }
+
         // Generates a proof of the NFT and returns it with any
         // remainder of the payment, if any payment was provided.
-        pub fn get_nft_proof(&mut self, mut payment: Option<Bucket>) -> (NonFungibleProof, Option<Bucket>) {
+        pub fn get_nft_proof(
+            &mut self,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>) {
+            // Generate the proof and return it with any remainder from the payment
+            let mut nflid_set = IndexSet::new();
+            nflid_set.insert(self.nft_id.local_id().clone());
+
+            self.get_nft_proofs(nflid_set, payment, holding_proof)
+        }
+
+        // Generates a proof of the NFT like get_nft_proof, and also returns a non-transferable receipt
+        // recording the issuance and the fee paid for it.
+        pub fn get_nft_proof_with_receipt(
+            &mut self,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>, NonFungibleBucket) {
+            let mut nflid_set = IndexSet::new();
+            nflid_set.insert(self.nft_id.local_id().clone());
+            let (proof, remainder, paid) = self.issue_proofs(nflid_set, payment, holding_proof);
+
+            let receipt = ResourceManager::from(self.issuance_receipt_resource)
+                .mint_ruid_non_fungible(IssuanceReceipt {
+                    nft_id: self.nft_id.clone(),
+                    fee_resource: paid.as_ref().map(|paid| paid.resource),
+                    amount_paid: paid.as_ref().map(|paid| paid.amount).unwrap_or(Decimal::ZERO),
+                    ticket_id: paid.and_then(|paid| paid.ticket_id),
+                    issued_at: Clock::current_time_rounded_to_seconds(),
+                    transaction_hash: Runtime::transaction_hash(),
+                })
+                .as_non_fungible();
+
+            (proof, remainder, receipt)
+        }
+
+        // Generates a single proof of several NFTs held by the component and returns it with any
+        // remainder of the payment, if any payment was provided.
+        pub fn get_nft_proofs(
+            &mut self,
+            ids: IndexSet<NonFungibleLocalId>,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>) {
+            let (proof, remainder, _) = self.issue_proofs(ids, payment, holding_proof);
+
+            (proof, remainder)
+        }
+
+        // Generates a proof of any NFT of the requested resource held by the component, for gates
+        // that only check for membership of a collection. NFTs are picked in turn.
+        pub fn get_collection_proof(
+            &mut self,
+            resource: ResourceAddress,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>) {
             assert!(
-                self.nft_vault.amount() == Decimal::ONE,
-                "This component is no longer active."
+                resource == self.nft_id.resource_address(),
+                "This component does not hold NFTs of this resource."
+            );
+
+            let held_ids = self.nft_vault.non_fungible_local_ids(u32::MAX);
+            assert!(!held_ids.is_empty(), "This component is no longer active.");
+
+            // Pick the next NFT in line
+            let index = (self.collection_cursor % held_ids.len() as u64) as usize;
+            self.collection_cursor += 1;
+
+            let mut nflid_set = IndexSet::new();
+            nflid_set.insert(held_ids.get_index(index).unwrap().clone());
+
+            self.get_nft_proofs(nflid_set, payment, holding_proof)
+        }
+
+        // Calls an allowed target method on behalf of the caller, with a proof of the NFT in the
+        // auth zone of this component. The proof never leaves the component, so it can only be used
+        // for that call. Returns the buckets the target returned and any remainder of the payment.
+        pub fn call_with_proof(
+            &mut self,
+            target: ComponentAddress,
+            method: String,
+            args: Vec<u8>,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (Vec<Bucket>, Option<Bucket>) {
+            let target_method = TargetMethod {
+                component: target,
+                method,
+            };
+            assert!(
+                self.allowed_targets.contains(&target_method),
+                "Target method is not allowed"
+            );
+
+            self.assert_open_access();
+            let mut nflid_set = IndexSet::new();
+            nflid_set.insert(self.nft_id.local_id().clone());
+            self.assert_can_issue(&nflid_set, holding_proof);
+            self.record_issuance(IssuanceKind::Call, &nflid_set);
+
+            // Call the target with the proof in our own auth zone and drop it right after
+            LocalAuthZone::push(self.nft_vault.create_proof_of_non_fungibles(&nflid_set));
+            let output = ScryptoVmV1Api::object_call(
+                target.as_node_id(),
+                &target_method.method,
+                args,
             );
+            LocalAuthZone::drop_proofs();
+
+            // Targets can return buckets in any shape, e.g. a single bucket, a tuple or an Option.
+            // Any other owned object, such as a proof, cannot be returned.
+            let output = IndexedScryptoValue::from_vec(output).expect("Target returned an invalid value");
+            let buckets = output
+                .owned_nodes()
+                .iter()
+                .map(|node_id| {
+                    let blueprint_id = ScryptoVmV1Api::object_get_blueprint_id(node_id);
+                    assert!(
+                        blueprint_id.package_address == RESOURCE_PACKAGE
+                            && (blueprint_id.blueprint_name == FUNGIBLE_BUCKET_BLUEPRINT
+                                || blueprint_id.blueprint_name == NON_FUNGIBLE_BUCKET_BLUEPRINT),
+                        "Target returned something other than buckets"
+                    );
+
+                    Bucket(Own(*node_id))
+                })
+                .collect();
+
+            (buckets, self.collect_fee(payment, 1).0)
+        }
+
+        // Generates a proof of the NFT against a permit signed by the owner, which sets its own
+        // price. Returns the proof with any remainder of the payment, if any payment was provided.
+        pub fn get_nft_proof_with_permit(
+            &mut self,
+            signed_permit: SignedPermit,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>) {
+            let permit_key = self.permit_key.expect("This component does not accept permits!");
+            let SignedPermit { permit, signature } = signed_permit;
+
+            // Permits are signed for this component only
+            let message = scrypto_encode(&(Runtime::global_address(), permit.clone())).unwrap();
             assert!(
-                Clock::current_time_rounded_to_seconds() < self.end_timestamp,
-                "You can no longer get a proof of this NFT."
+                CryptoUtils::bls12381_v1_verify(message, permit_key, signature),
+                "Invalid permit signature"
+            );
+            assert!(
+                Clock::current_time_rounded_to_seconds() < permit.expires_at,
+                "Permit has expired"
             );
 
-            // Generate the proof and return it with any remainder from the payment
+            // Only the borrower the permit was issued to can use it
+            Runtime::assert_access_rule(rule!(require(permit.borrower.clone())));
+
+            let uses = self.permit_uses.get(&permit.nonce).map(|uses| *uses).unwrap_or(0);
+            assert!(uses < permit.max_uses, "Permit has been used up");
+            self.permit_uses.insert(permit.nonce, uses + 1);
+
+            let proof = self.issue_primary_proof(IssuanceKind::Permit, holding_proof);
+
+            // Return proof and either a payment remainder or None
+            (proof, self.take_fee(payment, permit.price, false).0)
+        }
+
+        // Generates a proof of the NFT for anyone who knows an active access code. The code is free
+        // of charge or asks the regular fee. Returns the proof with any remainder of the payment.
+        pub fn get_nft_proof_with_code(
+            &mut self,
+            code: String,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>) {
+            self.assert_open_access();
+            let code_hash = CryptoUtils::keccak256_hash(code.into_bytes());
+            let access_code = self
+                .access_codes
+                .get(&code_hash)
+                .map(|access_code| access_code.clone())
+                .expect("Invalid access code");
+            assert!(
+                Clock::current_time_rounded_to_seconds() < access_code.expires_at,
+                "Access code has expired"
+            );
+
+            let uses = self.access_code_uses.get(&code_hash).map(|uses| *uses).unwrap_or(0);
+            assert!(uses < access_code.max_uses, "Access code has been used up");
+            self.access_code_uses.insert(code_hash, uses + 1);
+
+            let proof = self.issue_primary_proof(IssuanceKind::Code, holding_proof);
+
+            // Return proof and either a payment remainder or None
+            if access_code.free {
+                (proof, payment)
+            } else {
+                (proof, self.collect_fee(payment, 1).0)
+            }
+        }
+
+        // Generates a proof of the NFT against a proof of a grant badge, which uses up one of
+        // its uses. Returns the proof with any remainder of the payment, if any payment was provided.
+        pub fn get_nft_proof_with_grant(
+            &mut self,
+            grant_proof: NonFungibleProof,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>) {
+            let grant = grant_proof
+                .check_with_message(self.grant_resource, "Invalid grant")
+                .non_fungible::<AccessGrant>();
+            let grant_id = grant.local_id().clone();
+            let grant_data = grant.data();
+
+            assert!(self.revoked_grants.get(&grant_id).is_none(), "Grant has been revoked");
+            assert!(
+                Clock::current_time_rounded_to_seconds() < grant_data.expires_at,
+                "Grant has expired"
+            );
+            assert!(grant_data.remaining_uses > 0, "Grant has been used up");
+
+            ResourceManager::from(self.grant_resource).update_non_fungible_data(
+                &grant_id,
+                "remaining_uses",
+                grant_data.remaining_uses - 1,
+            );
+
+            let proof = self.issue_primary_proof(IssuanceKind::Grant, holding_proof);
+
+            // Return proof and either a payment remainder or None
+            match grant_data.fee_override {
+                Some(fee) => (proof, self.take_fee(payment, fee, false).0),
+                None => (proof, self.collect_fee(payment, 1).0),
+            }
+        }
+
+        // Requests access to a proof of the NFT. The fee is held in escrow until the request is
+        // redeemed or reclaimed. Returns the request receipt with any remainder of the payment.
+        pub fn request_access(
+            &mut self,
+            mut payment: Option<Bucket>,
+            memo: String,
+        ) -> (NonFungibleBucket, Option<Bucket>) {
+            assert!(self.approval_window.is_some(), "This component does not take access requests!");
+
+            let receipt = ResourceManager::from(self.request_resource)
+                .mint_ruid_non_fungible(AccessRequest {
+                    memo,
+                    requested_at: Clock::current_time_rounded_to_seconds(),
+                    status: RequestStatus::Pending,
+                })
+                .as_non_fungible();
+
+            let fee = self.fee_info.as_ref().map(|fee_info| self.fee_per_nft(fee_info)).unwrap_or(Decimal::ZERO);
+            if let Some(escrow) = self.split_fee(&mut payment, fee) {
+                self.request_escrows
+                    .insert(receipt.non_fungible_local_id(), Vault::with_bucket(escrow));
+            }
+
+            (receipt, payment)
+        }
+
+        // Redeems an approved access request for a proof of the NFT. The escrowed fee is taken.
+        pub fn redeem_request(
+            &mut self,
+            receipt: NonFungibleBucket,
+            holding_proof: Option<FungibleProof>,
+        ) -> NonFungibleProof {
+            let (id, status) = self.request_status(&receipt);
+            match status {
+                RequestStatus::Approved { redeem_before } => assert!(
+                    Clock::current_time_rounded_to_seconds() < redeem_before,
+                    "The approval has expired"
+                ),
+                _ => panic!("Access request has not been approved"),
+            }
+
+            if let Some(escrow) = self.take_escrow(&id) {
+                self.deposit_fee(escrow);
+            }
+            receipt.burn();
+
+            self.issue_primary_proof(IssuanceKind::Request, holding_proof)
+        }
+
+        // Returns the escrowed fee of an access request that is pending, was rejected or whose
+        // approval expired
+        pub fn reclaim_request(&mut self, receipt: NonFungibleBucket) -> Option<Bucket> {
+            let (id, status) = self.request_status(&receipt);
+            if let RequestStatus::Approved { redeem_before } = status {
+                assert!(
+                    Clock::current_time_rounded_to_seconds() >= redeem_before,
+                    "Access request has been approved and can still be redeemed"
+                );
+            }
+
+            receipt.burn();
+
+            self.take_escrow(&id)
+        }
+
+        // Lends out the NFT itself, together with a receipt that cannot be deposited.
+        // The NFT must be returned through flash_repay_nft in the same transaction.
+        pub fn flash_borrow_nft(
+            &mut self,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleBucket, NonFungibleBucket) {
+            self.assert_open_access();
+            let mut nflid_set = IndexSet::new();
+            nflid_set.insert(self.nft_id.local_id().clone());
+            self.assert_can_issue(&nflid_set, holding_proof);
+            self.record_issuance(IssuanceKind::FlashLoan, &nflid_set);
+
+            let nft = self.nft_vault.take_non_fungible(self.nft_id.local_id());
+            let receipt = ResourceManager::from(self.flash_receipt_resource)
+                .mint_ruid_non_fungible(FlashLoanReceipt {
+                    nft_id: self.nft_id.clone(),
+                })
+                .as_non_fungible();
+
+            (nft, receipt)
+        }
+
+        // Takes back a flash loaned NFT, burns the receipt and takes the fee.
+        // Returns any remainder of the payment.
+        pub fn flash_repay_nft(
+            &mut self,
+            nft: NonFungibleBucket,
+            receipt: NonFungibleBucket,
+            payment: Option<Bucket>,
+        ) -> Option<Bucket> {
+            assert!(
+                receipt.resource_address() == self.flash_receipt_resource,
+                "Invalid receipt"
+            );
+            assert!(nft.amount() == Decimal::ONE, "Must return exactly 1 NFT!");
+
+            // Make sure the same NFT is returned
+            let receipt_data = receipt.non_fungible::<FlashLoanReceipt>().data();
+            let returned_id = NonFungibleGlobalId::new(nft.resource_address(), nft.non_fungible_local_id());
+            assert!(returned_id == receipt_data.nft_id, "Did not return the borrowed NFT");
+
+            self.nft_vault.put(nft);
+            receipt.burn();
+
+            self.collect_fee(payment, 1).0
+        }
+
+        // Borrowers of a component that requires approval must go through an access request
+        fn assert_open_access(&self) {
+            assert!(
+                self.approval_window.is_none(),
+                "This component requires an approved access request"
+            );
+        }
+
+        // Makes sure the access request exists and has not been approved or rejected yet
+        fn assert_request_pending(&self, id: &NonFungibleLocalId) {
+            let request = ResourceManager::from(self.request_resource).get_non_fungible_data::<AccessRequest>(id);
+            assert!(request.status == RequestStatus::Pending, "Access request is not pending");
+        }
+
+        // Returns the id and status of the access request in a receipt bucket
+        fn request_status(&self, receipt: &NonFungibleBucket) -> (NonFungibleLocalId, RequestStatus) {
+            assert!(
+                receipt.resource_address() == self.request_resource,
+                "Invalid access request receipt"
+            );
+            let request = receipt.non_fungible::<AccessRequest>();
+
+            (request.local_id().clone(), request.data().status)
+        }
+
+        // Takes the escrowed fee of an access request, if any
+        fn take_escrow(&mut self, id: &NonFungibleLocalId) -> Option<Bucket> {
+            self.request_escrows.get_mut(id).map(|mut escrow| escrow.take_all())
+        }
+
+        // Generates a single proof of several NFTs held by the component and takes the fee for it.
+        // Returns the proof, any remainder of the payment and what was paid.
+        fn issue_proofs(
+            &mut self,
+            ids: IndexSet<NonFungibleLocalId>,
+            payment: Option<Bucket>,
+            holding_proof: Option<FungibleProof>,
+        ) -> (NonFungibleProof, Option<Bucket>, Option<FeePayment>) {
+            assert!(!ids.is_empty(), "No NFTs were requested");
+            self.assert_open_access();
+            self.assert_can_issue(&ids, holding_proof);
+            self.record_issuance(IssuanceKind::Proof, &ids);
+
+            let proof = self.nft_vault.create_proof_of_non_fungibles(&ids);
+            let (remainder, paid) = self.collect_fee(payment, ids.len());
+
+            (proof, remainder, paid)
+        }
+
+        // Generates a proof of the NFT the component was instantiated with
+        fn issue_primary_proof(
+            &mut self,
+            kind: IssuanceKind,
+            holding_proof: Option<FungibleProof>,
+        ) -> NonFungibleProof {
             let mut nflid_set = IndexSet::new();
             nflid_set.insert(self.nft_id.local_id().clone());
-            let proof = self.nft_vault.create_proof_of_non_fungibles(
-                &nflid_set
-            );            
+            self.assert_can_issue(&nflid_set, holding_proof);
+            self.record_issuance(kind, &nflid_set);
+
+            self.nft_vault.create_proof_of_non_fungibles(&nflid_set)
+        }
+
+        // Adds an issuance to the usage statistics and the issuance log
+        fn record_issuance(&mut self, kind: IssuanceKind, ids: &IndexSet<NonFungibleLocalId>) {
+            let transaction_hash = Runtime::transaction_hash();
+            if self.transaction_policy == TransactionPolicy::SingleIssuance {
+                assert!(
+                    self.last_issuance_transaction != Some(transaction_hash),
+                    "Only one issuance is allowed per transaction"
+                );
+            }
+            self.last_issuance_transaction = Some(transaction_hash);
+
+            let now = Clock::current_time_rounded_to_seconds();
+
+            self.issuance_log.insert(
+                self.usage_stats.total_proofs,
+                IssuanceRecord {
+                    kind,
+                    nft_ids: ids.clone(),
+                    issued_at: now,
+                },
+            );
+            self.usage_stats.total_proofs += 1;
+            self.usage_stats.first_issued_at.get_or_insert(now);
+            self.usage_stats.last_issued_at = Some(now);
+        }
+
+        // Puts a fee in the fee vault and adds it to the fees collected
+        fn deposit_fee(&mut self, fee: Bucket) {
+            let resource = fee.resource_address();
+            let collected = self.get_fees_collected(resource) + fee.amount();
+            self.fees_collected.insert(resource, collected);
+
+            self.fee_vault.as_mut().unwrap().put(fee);
+        }
+
+        // Takes the fee for the given number of NFTs from the payment, if a fee is asked,
+        // and returns the remainder and what was paid
+        fn collect_fee(&mut self, payment: Option<Bucket>, nft_count: usize) -> (Option<Bucket>, Option<FeePayment>) {
+            // A ticket pays for a whole issuance. Without a fee, tickets are the only way to pay.
+            if let Some(ticket_payment) = self.ticket_payment.clone() {
+                let paid_with_ticket = payment
+                    .as_ref()
+                    .is_some_and(|payment| payment.resource_address() == ticket_payment.resource);
+                if paid_with_ticket || self.fee_info.is_none() {
+                    return self.take_ticket(&ticket_payment, payment);
+                }
+            }
+
+            let fee = match (&self.fee_info, self.bundle_fee) {
+                (Some(_), Some(bundle_fee)) if nft_count > 1 => bundle_fee,
+                (Some(fee_info), _) => self.fee_per_nft(fee_info) * Decimal::from(nft_count as u64),
+                (None, _) => Decimal::ZERO,
+            };
+
+            self.take_fee(payment, fee, true)
+        }
+
+        // Whether the transaction already paid the regular fee or a ticket and further proofs in
+        // it are free, because the component charges once per transaction
+        fn already_paid(&self) -> bool {
+            self.transaction_policy == TransactionPolicy::ChargeOnce
+                && self.last_paid_transaction == Some(Runtime::transaction_hash())
+        }
+
+        // Takes a ticket from the payment, checks its data if required, and burns or keeps it.
+        // Returns the remainder and the ticket that was paid.
+        fn take_ticket(
+            &mut self,
+            ticket_payment: &TicketPayment,
+            mut payment: Option<Bucket>,
+        ) -> (Option<Bucket>, Option<FeePayment>) {
+            // Holders may be exempted from the fee, their holding is checked before issuing
+            if let Some(HoldingRequirement { waives_fee: true, .. }) = self.holding_requirement {
+                return (payment, None);
+            }
+            if self.already_paid() {
+                return (payment, None);
+            }
+
+            let tickets = payment.take().expect("No ticket was provided");
+            assert!(
+                tickets.resource_address() == ticket_payment.resource,
+                "Did not pay with a ticket!"
+            );
+            let mut tickets = tickets.as_non_fungible();
+
+            // Pick a ticket that passes the data checks, if they are required
+            let ticket_id = tickets
+                .non_fungible_local_ids()
+                .into_iter()
+                .find(|id| !ticket_payment.check_data || Self::ticket_is_valid(ticket_payment.resource, id))
+                .expect("No valid ticket was provided");
+            let ticket = tickets.take_non_fungible(&ticket_id);
+            let paid = FeePayment {
+                resource: ticket_payment.resource,
+                amount: Decimal::ONE,
+                ticket_id: Some(ticket_id),
+            };
+            let payment = Some(tickets.into());
+
+            if ticket_payment.burn {
+                ticket.burn();
+            } else if self.ticket_vaults.get(&ticket_payment.resource).is_some() {
+                self.ticket_vaults.get_mut(&ticket_payment.resource).unwrap().put(ticket.into());
+            } else {
+                self.ticket_vaults.insert(ticket_payment.resource, Vault::with_bucket(ticket.into()));
+                self.ticket_resources.insert(ticket_payment.resource);
+            }
+            self.last_paid_transaction = Some(Runtime::transaction_hash());
+
+            (payment, Some(paid))
+        }
+
+        // Whether a ticket has not expired and is not meant for another component
+        fn ticket_is_valid(resource: ResourceAddress, id: &NonFungibleLocalId) -> bool {
+            let data = ResourceManager::from(resource).get_non_fungible_data::<ProofTicket>(id);
+            let not_expired = data
+                .expires_at
+                .is_none_or(|expires_at| Clock::current_time_rounded_to_seconds() < expires_at);
+            let for_this_component = data
+                .component
+                .is_none_or(|component| component == Runtime::global_address());
+
+            not_expired && for_this_component
+        }
+
+        // The fee per NFT: either the fixed fee amount, or the reference amount converted at the
+        // oracle price. Oracles return the price of a resource in the reference unit and when it
+        // was last updated, through get_price(resource) -> (Decimal, Instant).
+        fn fee_per_nft(&self, fee_info: &FeeInfo) -> Decimal {
+            let pricing = match self.oracle_pricing {
+                Some(ref pricing) => pricing,
+                None => return fee_info.amount,
+            };
+
+            let (price, updated_at) = Global::<AnyComponent>::from(pricing.oracle)
+                .call::<_, (Decimal, Instant)>("get_price", &(fee_info.resource,));
+
+            // Stale prices are replaced by the fallback price, if there is one
+            let now = Clock::current_time_rounded_to_seconds();
+            assert!(
+                updated_at.seconds_since_unix_epoch <= now.seconds_since_unix_epoch,
+                "Oracle price is from the future"
+            );
+            let age = now.seconds_since_unix_epoch - updated_at.seconds_since_unix_epoch;
+            let price = if age <= pricing.max_age {
+                price
+            } else {
+                pricing.fallback_price.expect("Oracle price is stale")
+            };
+            assert!(price > Decimal::ZERO, "Oracle price must be higher than 0");
+
+            pricing.reference_amount / price
+        }
+
+        // Takes the given fee from the payment, if a fee is asked, and returns the remainder and
+        // what was paid. Only a regular fee, not a permit price or fee override, pays for the
+        // rest of the transaction when charging once.
+        fn take_fee(
+            &mut self,
+            mut payment: Option<Bucket>,
+            fee: Decimal,
+            regular: bool,
+        ) -> (Option<Bucket>, Option<FeePayment>) {
+            if self.already_paid() {
+                return (payment, None);
+            }
+
+            let paid = self.split_fee(&mut payment, fee).map(|fee_payment| {
+                let paid = FeePayment {
+                    resource: fee_payment.resource_address(),
+                    amount: fee_payment.amount(),
+                    ticket_id: None,
+                };
+                self.deposit_fee(fee_payment);
+
+                paid
+            });
+            if regular && paid.as_ref().is_some_and(|paid| paid.amount > Decimal::ZERO) {
+                self.last_paid_transaction = Some(Runtime::transaction_hash());
+            }
+
+            (payment, paid)
+        }
+
+        // Splits the given fee off the payment, if a fee is asked
+        fn split_fee(&self, payment: &mut Option<Bucket>, fee: Decimal) -> Option<Bucket> {
+            // Holders may be exempted from the fee, their holding is checked before issuing
+            if let Some(HoldingRequirement { waives_fee: true, .. }) = self.holding_requirement {
+                return None;
+            }
 
             // If a payment is required
-            if let Some(ref fee_info) = self.fee_info {
-                // Make sure a payment was provided
-                assert!(payment.is_some(), "No payment was provided");
-                // Make sure it was the correct resource
+            let fee_info = self.fee_info.as_ref()?;
+            // Make sure a payment was provided
+            let payment = payment.as_mut().expect("No payment was provided");
+            // Payments in another resource are swapped into the fee resource, if a swap hook is set
+            if let Some(ref swap_hook) = self.swap_hook {
+                if payment.resource_address() != fee_info.resource && fee > Decimal::ZERO {
+                    return Some(Self::swap_for_fee(swap_hook, payment, fee_info.resource, fee));
+                }
+            }
+            // Make sure it was the correct resource
+            assert!(
+                payment.resource_address() == fee_info.resource,
+                "Did not pay with correct resource!"
+            );
+            // Make sure it was the correct amount
+            assert!(payment.amount() >= fee, "Did not pay enough!");
+
+            // Take the payment, rounded up to the divisibility of the fee resource
+            Some(payment.take_advanced(
+                fee,
+                WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+            ))
+        }
+
+        // Swaps just enough of the payment into the fee resource to pay the fee. Swap components
+        // implement spot_price(input_resource, output_resource) -> Decimal, the output per unit of
+        // input, quote_input(input_resource, output_resource, output_amount) -> Decimal, the input
+        // needed for an output amount, and swap(input, output_resource) -> Bucket.
+        fn swap_for_fee(
+            swap_hook: &SwapHook,
+            payment: &mut Bucket,
+            fee_resource: ResourceAddress,
+            fee: Decimal,
+        ) -> Bucket {
+            let swap_component = Global::<AnyComponent>::from(swap_hook.component);
+            let payment_resource = payment.resource_address();
+            let spot_price = swap_component.call::<_, Decimal>("spot_price", &(payment_resource, fee_resource));
+            assert!(spot_price > Decimal::ZERO, "Swap spot price must be higher than 0");
+            let quote = swap_component.call::<_, Decimal>("quote_input", &(payment_resource, fee_resource, fee));
+
+            // The quote may ask more than the fee at the spot price, but not more than the slippage
+            // limit allows
+            assert!(
+                quote <= fee / spot_price * (Decimal::ONE + swap_hook.max_slippage),
+                "Swap exceeded the slippage limit"
+            );
+
+            let input = payment.take_advanced(
+                quote.min(payment.amount()),
+                WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+            );
+            let output = swap_component.call::<_, Bucket>("swap", &(input, fee_resource));
+            assert!(output.resource_address() == fee_resource, "Swap returned the wrong resource");
+            assert!(output.amount() >= fee, "Swap returned less than the fee");
+
+            output
+        }
+
+        // Reads the data of an NFT as raw SBOR, without having to know its schema
+        fn non_fungible_data(resource: ResourceAddress, id: &NonFungibleLocalId) -> Vec<u8> {
+            ScryptoVmV1Api::object_call(
+                resource.as_node_id(),
+                NON_FUNGIBLE_RESOURCE_MANAGER_GET_NON_FUNGIBLE_IDENT,
+                scrypto_encode(&NonFungibleResourceManagerGetNonFungibleInput { id: id.clone() }).unwrap(),
+            )
+        }
+
+        fn non_fungible_data_hash(resource: ResourceAddress, id: &NonFungibleLocalId) -> Hash {
+            CryptoUtils::keccak256_hash(Self::non_fungible_data(resource, id))
+        }
+
+        // Checks whether the component is currently allowed to hand out the NFTs or a proof of them
+        fn assert_can_issue(&self, ids: &IndexSet<NonFungibleLocalId>, holding_proof: Option<FungibleProof>) {
+            assert!(
+                self.nft_vault.amount() > Decimal::ZERO,
+                "This component is no longer active."
+            );
+            for id in ids {
                 assert!(
-                    payment.as_ref().unwrap().resource_address() == fee_info.resource,
-                    "Did not pay with correct resource!"
+                    self.nft_vault.contains_non_fungible(id),
+                    "This component does not hold the requested NFT."
                 );
-                // Make sure it was the correct amount
-                assert!(payment.as_mut().unwrap().amount() >= fee_info.amount, "Did not pay enough!");
-    
-                // Take the payment
-                self.fee_vault.as_mut().unwrap().put(payment.as_mut().unwrap().take(fee_info.amount));
+
+                // Stop if the data of the NFT changed since the owner last accepted it
+                if self.guard_nft_data {
+                    let snapshot = *self.nft_data_hashes.get(id).expect("No snapshot of the NFT data");
+                    assert!(
+                        Self::non_fungible_data_hash(self.nft_id.resource_address(), id) == snapshot,
+                        "The NFT data has changed. The owner must acknowledge the change first."
+                    );
+                }
             }
 
-            // Return proof and either a payment remainder or None
-            (proof, payment)
+            // If callers are restricted, the caller must be one of them
+            if !self.allowed_callers.is_empty() {
+                let requirements: Vec<ResourceOrNonFungible> = self.allowed_callers
+                    .iter()
+                    .map(AllowedCaller::requirement)
+                    .collect();
+                Runtime::assert_access_rule(rule!(require_any_of(requirements)));
+            }
+
+            // The caller must present a proof of holding enough of the required token
+            if let Some(ref holding_requirement) = self.holding_requirement {
+                let holding_proof = holding_proof
+                    .expect("No proof of holding was provided")
+                    .check_with_message(holding_requirement.resource, "Proof of holding is of the wrong resource");
+                assert!(
+                    holding_proof.amount() >= holding_requirement.amount,
+                    "Does not hold enough of the required token"
+                );
+                holding_proof.drop();
+            } else if let Some(holding_proof) = holding_proof {
+                holding_proof.drop();
+            }
+
+            let now = Clock::current_time_rounded_to_seconds();
+            assert!(
+                now < self.end_timestamp,
+                "You can no longer get a proof of this NFT."
+            );
+
+            if let Some(interval) = self.heartbeat_interval {
+                assert!(
+                    now <= self.last_heartbeat.add_seconds(interval).unwrap(),
+                    "The owner has not sent a heartbeat in time."
+                );
+            }
         }
     }
 }
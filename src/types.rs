@@ -4,4 +4,232 @@ use scrypto::prelude::*;
 pub struct FeeInfo {
     pub resource: ResourceAddress,
     pub amount: Decimal,
-}
\ No newline at end of file
+}
+
+#[derive(ScryptoSbor, Clone, Default)]
+pub struct InstantiateOptions {
+    pub operator_rule: Option<AccessRule>,
+    pub owner_badge_resource: Option<ResourceAddress>,
+    pub recovery: Option<RecoveryInfo>,
+    pub settlement: Option<SettlementInfo>,
+    pub heartbeat_interval: Option<i64>,
+    pub guard_nft_data: bool,
+    pub risk_policy: RiskPolicy,
+    pub approval_window: Option<i64>,
+    pub transaction_policy: TransactionPolicy,
+}
+
+#[derive(ScryptoSbor, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RiskPolicy {
+    #[default]
+    Flag,
+    Reject,
+}
+
+#[derive(ScryptoSbor, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TransactionPolicy {
+    #[default]
+    ChargeEach,
+    ChargeOnce,
+    SingleIssuance,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct RiskFlags {
+    pub recallable: bool,
+    pub freezable: bool,
+}
+
+impl RiskFlags {
+    pub fn to_strings(&self) -> Vec<String> {
+        let mut flags = vec![];
+        if self.recallable {
+            flags.push("recallable".to_string());
+        }
+        if self.freezable {
+            flags.push("freezable".to_string());
+        }
+
+        flags
+    }
+}
+
+#[derive(ScryptoSbor, Clone)]
+pub struct RecoveryInfo {
+    pub rule: AccessRule,
+    pub delay: i64,
+}
+
+#[derive(ScryptoSbor, NonFungibleData)]
+pub struct OwnerBadgeData {
+    pub component: ComponentAddress,
+    pub nft_id: NonFungibleGlobalId,
+    pub created_at: Instant,
+    #[mutable]
+    pub label: String,
+}
+
+#[derive(ScryptoSbor, Clone)]
+pub struct SettlementInfo {
+    pub account: ComponentAddress,
+    pub keeper_reward: Option<Decimal>,
+}
+
+#[derive(ScryptoSbor, NonFungibleData)]
+pub struct FlashLoanReceipt {
+    pub nft_id: NonFungibleGlobalId,
+}
+
+#[derive(ScryptoSbor, NonFungibleData)]
+pub struct AccessGrant {
+    pub expires_at: Instant,
+    #[mutable]
+    pub remaining_uses: u32,
+    pub fee_override: Option<Decimal>,
+}
+
+#[derive(ScryptoSbor, Clone, PartialEq, Eq, Debug)]
+pub enum RequestStatus {
+    Pending,
+    Approved { redeem_before: Instant },
+    Rejected,
+}
+
+#[derive(ScryptoSbor, NonFungibleData)]
+pub struct AccessRequest {
+    pub memo: String,
+    pub requested_at: Instant,
+    #[mutable]
+    pub status: RequestStatus,
+}
+
+#[derive(ScryptoSbor, NonFungibleData)]
+pub struct IssuanceReceipt {
+    pub nft_id: NonFungibleGlobalId,
+    pub fee_resource: Option<ResourceAddress>,
+    pub amount_paid: Decimal,
+    pub ticket_id: Option<NonFungibleLocalId>,
+    pub issued_at: Instant,
+    pub transaction_hash: Hash,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct FeePayment {
+    pub resource: ResourceAddress,
+    pub amount: Decimal,
+    pub ticket_id: Option<NonFungibleLocalId>,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct NftInfo {
+    pub id: NonFungibleGlobalId,
+    pub data: Vec<u8>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub icon_url: Option<UncheckedUrl>,
+}
+
+#[derive(ScryptoSbor, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AllowedCaller {
+    Package(PackageAddress),
+    Blueprint(BlueprintId),
+    Component(ComponentAddress),
+}
+
+impl AllowedCaller {
+    // The virtual badge present in the auth zone when this caller calls a method
+    pub fn requirement(&self) -> ResourceOrNonFungible {
+        match self {
+            AllowedCaller::Package(package_address) => package_of_direct_caller(*package_address),
+            AllowedCaller::Blueprint(blueprint_id) => global_caller(blueprint_id.clone()),
+            AllowedCaller::Component(component_address) => global_caller(*component_address),
+        }
+    }
+}
+
+#[derive(ScryptoSbor, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TargetMethod {
+    pub component: ComponentAddress,
+    pub method: String,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct OraclePricing {
+    pub oracle: ComponentAddress,
+    pub reference_amount: Decimal,
+    pub max_age: i64,
+    pub fallback_price: Option<Decimal>,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct SwapHook {
+    pub component: ComponentAddress,
+    pub max_slippage: Decimal,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct TicketPayment {
+    pub resource: ResourceAddress,
+    pub check_data: bool,
+    pub burn: bool,
+}
+
+#[derive(ScryptoSbor, NonFungibleData)]
+pub struct ProofTicket {
+    pub expires_at: Option<Instant>,
+    pub component: Option<ComponentAddress>,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct HoldingRequirement {
+    pub resource: ResourceAddress,
+    pub amount: Decimal,
+    pub waives_fee: bool,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct Permit {
+    pub borrower: NonFungibleGlobalId,
+    pub nonce: u64,
+    pub expires_at: Instant,
+    pub max_uses: u32,
+    pub price: Decimal,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct SignedPermit {
+    pub permit: Permit,
+    pub signature: Bls12381G2Signature,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct AccessCode {
+    pub max_uses: u32,
+    pub expires_at: Instant,
+    pub free: bool,
+}
+
+#[derive(ScryptoSbor, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IssuanceKind {
+    Proof,
+    Call,
+    Permit,
+    Code,
+    Grant,
+    Request,
+    FlashLoan,
+}
+
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct IssuanceRecord {
+    pub kind: IssuanceKind,
+    pub nft_ids: IndexSet<NonFungibleLocalId>,
+    pub issued_at: Instant,
+}
+
+#[derive(ScryptoSbor, Clone, Debug, Default)]
+pub struct UsageStats {
+    pub total_proofs: u64,
+    pub first_issued_at: Option<Instant>,
+    pub last_issued_at: Option<Instant>,
+}
@@ -0,0 +1,4 @@
+pub mod mock_amm;
+pub mod mock_borrower;
+pub mod mock_gate;
+pub mod mock_oracle;
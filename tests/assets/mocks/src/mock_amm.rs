@@ -0,0 +1,69 @@
+use scrypto::prelude::*;
+
+// An AMM that swaps anything into a single resource at a fixed rate, minus an adjustable slippage
+// that is included in its quotes and an adjustable shortfall that is not
+#[blueprint]
+mod mock_amm {
+    struct MockAmm {
+        output_vault: Vault,
+        input_vaults: KeyValueStore<ResourceAddress, Vault>,
+        rate: Decimal,
+        slippage: Decimal,
+        shortfall: Decimal,
+    }
+
+    impl MockAmm {
+        pub fn instantiate(output: Bucket, rate: Decimal) -> Global<MockAmm> {
+            Self {
+                output_vault: Vault::with_bucket(output),
+                input_vaults: KeyValueStore::new(),
+                rate,
+                slippage: Decimal::ZERO,
+                shortfall: Decimal::ZERO,
+            }
+            .instantiate()
+            .prepare_to_globalize(OwnerRole::None)
+            .globalize()
+        }
+
+        pub fn set_slippage(&mut self, slippage: Decimal) {
+            self.slippage = slippage;
+        }
+
+        pub fn set_shortfall(&mut self, shortfall: Decimal) {
+            self.shortfall = shortfall;
+        }
+
+        pub fn spot_price(&self, _input_resource: ResourceAddress, output_resource: ResourceAddress) -> Decimal {
+            assert!(output_resource == self.output_vault.resource_address(), "Unsupported output resource");
+
+            self.rate
+        }
+
+        pub fn quote_input(
+            &self,
+            _input_resource: ResourceAddress,
+            output_resource: ResourceAddress,
+            output_amount: Decimal,
+        ) -> Decimal {
+            assert!(output_resource == self.output_vault.resource_address(), "Unsupported output resource");
+
+            output_amount / (self.rate * (Decimal::ONE - self.slippage))
+        }
+
+        pub fn swap(&mut self, input: Bucket, output_resource: ResourceAddress) -> Bucket {
+            assert!(output_resource == self.output_vault.resource_address(), "Unsupported output resource");
+
+            let output_amount =
+                input.amount() * self.rate * (Decimal::ONE - self.slippage) * (Decimal::ONE - self.shortfall);
+            let input_resource = input.resource_address();
+            if self.input_vaults.get(&input_resource).is_some() {
+                self.input_vaults.get_mut(&input_resource).unwrap().put(input);
+            } else {
+                self.input_vaults.insert(input_resource, Vault::with_bucket(input));
+            }
+
+            self.output_vault.take(output_amount)
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use scrypto::prelude::*;
+
+// A dApp that borrows a proof from a Flash Proof component on-ledger
+#[blueprint]
+mod mock_borrower {
+    struct MockBorrower {}
+
+    impl MockBorrower {
+        pub fn instantiate() -> Global<MockBorrower> {
+            Self {}
+                .instantiate()
+                .prepare_to_globalize(OwnerRole::None)
+                .globalize()
+        }
+
+        pub fn borrow(&mut self, flash_proof: ComponentAddress) {
+            let (proof, _) = Global::<AnyComponent>::from(flash_proof)
+                .call::<_, (NonFungibleProof, Option<Bucket>)>("get_nft_proof", &(None::<Bucket>, None::<FungibleProof>));
+
+            proof.drop();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use scrypto::prelude::*;
+
+// A dApp gate that hands out a visitor token to anyone holding a specific NFT
+#[blueprint]
+mod mock_gate {
+    enable_method_auth! {
+        roles {
+            member => updatable_by: [];
+        },
+        methods {
+            enter => restrict_to: [member];
+            enter_with_guest => restrict_to: [member];
+        }
+    }
+
+    struct MockGate {
+        visitor_token: ResourceAddress,
+    }
+
+    impl MockGate {
+        pub fn instantiate(required_nft: NonFungibleGlobalId) -> Global<MockGate> {
+            let (address_reservation, component_address) = Runtime::allocate_component_address(MockGate::blueprint_id());
+
+            let visitor_token = ResourceBuilder::new_fungible(OwnerRole::None)
+                .divisibility(DIVISIBILITY_NONE)
+                .mint_roles(mint_roles!(
+                    minter => rule!(require(global_caller(component_address)));
+                    minter_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply()
+                .address();
+
+            Self { visitor_token }
+                .instantiate()
+                .prepare_to_globalize(OwnerRole::None)
+                .roles(roles!(
+                    member => rule!(require(required_nft));
+                ))
+                .with_address(address_reservation)
+                .globalize()
+        }
+
+        pub fn enter(&mut self) -> Bucket {
+            ResourceManager::from(self.visitor_token).mint(1)
+        }
+
+        pub fn enter_with_guest(&mut self) -> (Bucket, Option<Bucket>) {
+            let visitor_token = ResourceManager::from(self.visitor_token);
+
+            (visitor_token.mint(1), Some(visitor_token.mint(1)))
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use scrypto::prelude::*;
+
+// A price oracle that returns whatever price was last set for a resource
+#[blueprint]
+mod mock_oracle {
+    struct MockOracle {
+        prices: KeyValueStore<ResourceAddress, (Decimal, Instant)>,
+    }
+
+    impl MockOracle {
+        pub fn instantiate() -> Global<MockOracle> {
+            Self {
+                prices: KeyValueStore::new(),
+            }
+            .instantiate()
+            .prepare_to_globalize(OwnerRole::None)
+            .globalize()
+        }
+
+        pub fn set_price(&mut self, resource: ResourceAddress, price: Decimal, updated_at: Instant) {
+            self.prices.insert(resource, (price, updated_at));
+        }
+
+        pub fn get_price(&self, resource: ResourceAddress) -> (Decimal, Instant) {
+            *self.prices.get(&resource).expect("No price for this resource")
+        }
+    }
+}
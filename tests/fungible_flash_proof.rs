@@ -0,0 +1,158 @@
+use scrypto_test::prelude::*;
+use flash_proof::types::FeeInfo;
+
+use flash_proof::fungible_flash_proof::fungible_flash_proof_test::*;
+
+#[test]
+fn can_get_proof_of_amount_and_pay_per_unit() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, deposit_resource) =
+        create_environment(dec!(1000), dec!(500), Some(dec!(2)), 30, None)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(250), Mock, &mut env)?;
+
+    // Act
+    let (proof, remainder) = component.get_proof(dec!(100), Some(payment), &mut env)?;
+
+    // Assert
+    assert!(
+        proof.0.resource_address(&mut env)? == deposit_resource,
+        "Got a proof of the wrong resource"
+    );
+    assert!(
+        proof.0.amount(&mut env)? == dec!(100),
+        "Got a proof of the wrong amount"
+    );
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(50),
+        "Did not get back the expected amount"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_above_max_amount() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, _) =
+        create_environment(dec!(1000), dec!(500), None, 30, None)?;
+
+    // Act
+    let result = component.get_proof(dec!(501), None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof of more than the maximum amount");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_while_underpaying() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, _) =
+        create_environment(dec!(1000), dec!(500), Some(dec!(2)), 30, None)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(199), Mock, &mut env)?;
+
+    // Act
+    let result = component.get_proof(dec!(100), Some(payment), &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof while paying too little");
+
+    Ok(())
+}
+
+#[test]
+fn can_withdraw_deposit_and_fees() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment(dec!(1000), dec!(500), Some(dec!(2)), 30, None)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(200), Mock, &mut env)?;
+    let _ = component.get_proof(dec!(100), Some(payment), &mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    // Act
+    let deposit = component.withdraw_deposit(&mut env)?;
+    let fees = component.withdraw_fees(&mut env)?;
+
+    // Assert
+    assert!(
+        deposit.0.amount(&mut env)? == dec!(1000),
+        "Got back wrong deposit amount"
+    );
+    assert!(
+        fees.0.amount(&mut env)? == dec!(200),
+        "Got back wrong fee amount"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn operator_can_update_max_amount_but_not_withdraw() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, _) =
+        create_environment(dec!(1000), dec!(500), None, 30, Some(rule!(allow_all)))?;
+
+    // Act
+    component.update_max_amount(dec!(800), &mut env)?;
+    let proof_result = component.get_proof(dec!(800), None, &mut env);
+    let withdraw_result = component.withdraw_deposit(&mut env);
+
+    // Assert
+    assert!(proof_result.is_ok(), "Max amount was not updated by the operator");
+    assert!(withdraw_result.is_err(), "Operator was able to withdraw the deposit");
+
+    Ok(())
+}
+
+fn create_environment(
+    deposit_amount: Decimal,
+    max_amount: Decimal,
+    fee_per_unit: Option<Decimal>,
+    days_in_future: i64,
+    operator_rule: Option<AccessRule>,
+) -> Result<
+    (
+        TestEnvironment<InMemorySubstateDatabase>,
+        FungibleFlashProof,
+        Bucket,
+        ResourceAddress
+    ),
+    RuntimeError,
+> {
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    // Create timestamp
+    let timestamp = env.get_current_time().add_days(days_in_future).unwrap();
+
+    // Create the deposit
+    let deposit = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(deposit_amount, &mut env)?;
+    let deposit_resource = deposit.resource_address(&mut env)?;
+
+    // Fee info
+    let fee_info = fee_per_unit.map(|amount| FeeInfo {
+        resource: XRD,
+        amount,
+    });
+
+    // Instantiate component with the deposit
+    let (component, owner_badge) = FungibleFlashProof::instantiate(
+        FungibleBucket(deposit),
+        max_amount,
+        fee_info,
+        timestamp,
+        operator_rule,
+        package_address,
+        &mut env,
+    )?;
+
+    Ok((env, component, owner_badge.into(), deposit_resource))
+}
@@ -1,6 +1,12 @@
+use std::path::Path;
+
 use scrypto::NonFungibleData;
 use scrypto_test::prelude::*;
-use flash_proof::types::FeeInfo;
+use flash_proof::types::{
+    AccessCode, AllowedCaller, FeeInfo, HoldingRequirement, InstantiateOptions, IssuanceKind, IssuanceReceipt,
+    OraclePricing, OwnerBadgeData, Permit, ProofTicket, RecoveryInfo, RiskPolicy, SettlementInfo, SignedPermit,
+    SwapHook, TargetMethod, TicketPayment, TransactionPolicy,
+};
 
 use flash_proof::flash_proof_test::*;
 
@@ -9,6 +15,13 @@ pub struct FakeNFT {
     pub name: String,
 }
 
+#[derive(ScryptoSbor, NonFungibleData, Clone, Debug)]
+pub struct MutableFakeNFT {
+    pub name: String,
+    #[mutable]
+    pub banned: bool,
+}
+
 #[test]
 fn can_withdraw_nft() -> Result<(), RuntimeError> {
     // Arrange
@@ -63,7 +76,7 @@ fn can_get_proof_within_timeframe() -> Result<(), RuntimeError> {
     let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
 
     // Act
-    let (proof, remainder) = component.get_nft_proof(Some(payment), &mut env)?;
+    let (proof, remainder) = component.get_nft_proof(Some(payment), None, &mut env)?;
 
     // Assert
     assert!(
@@ -91,7 +104,7 @@ fn can_get_proof_within_timeframe_if_no_fee_required() -> Result<(), RuntimeErro
         create_environment_without_fee(30)?;
 
     // Act
-    let (proof, _) = component.get_nft_proof(None, &mut env)?;
+    let (proof, _) = component.get_nft_proof(None, None, &mut env)?;
 
     // Assert
     let proof_address = proof.0.resource_address(&mut env)?;
@@ -117,7 +130,7 @@ fn payment_is_returned_from_no_fee_component() -> Result<(), RuntimeError> {
     let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
 
     // Act
-    let (_, remainder) = component.get_nft_proof(Some(payment), &mut env)?;
+    let (_, remainder) = component.get_nft_proof(Some(payment), None, &mut env)?;
 
     // Assert
     assert!(
@@ -140,7 +153,7 @@ fn can_withdraw_fee() -> Result<(), RuntimeError> {
     LocalAuthZone::push(owner_proof, &mut env)?;
 
     // Act
-    let _ = component.get_nft_proof(Some(payment), &mut env)?; // Make payment
+    let _ = component.get_nft_proof(Some(payment), None, &mut env)?; // Make payment
     let fee = component.withdraw_fees(&mut env)?; // Withdraw fees
 
     // Assert
@@ -164,7 +177,7 @@ fn cannot_withdraw_fee_if_no_fee_is_asked() -> Result<(), RuntimeError> {
     LocalAuthZone::push(owner_proof, &mut env)?;
 
     // Act
-    let _ = component.get_nft_proof(Some(payment), &mut env)?; // Make payment
+    let _ = component.get_nft_proof(Some(payment), None, &mut env)?; // Make payment
     let result = component.withdraw_fees(&mut env); // Withdraw fees
 
     // Assert
@@ -213,7 +226,7 @@ fn can_update_fee_to_0_and_generate_proof() -> Result<(), RuntimeError> {
 
     // Act
     component.update_fee(dec!(0), &mut env)?; // Withdraw fees
-    let (_, remainder) = component.get_nft_proof(Some(payment), &mut env)?;
+    let (_, remainder) = component.get_nft_proof(Some(payment), None, &mut env)?;
 
     // Assert
     assert!(
@@ -239,7 +252,7 @@ fn can_update_fee_to_0_and_send_empty_bucket() -> Result<(), RuntimeError> {
 
     // Act
     component.update_fee(dec!(0), &mut env)?; // Withdraw fees
-    let (_, remainder) = component.get_nft_proof(Some(payment), &mut env)?;
+    let (_, remainder) = component.get_nft_proof(Some(payment), None, &mut env)?;
 
     // Assert
     assert!(
@@ -316,8 +329,8 @@ fn cannot_get_proof_with_wrong_payment() -> Result<(), RuntimeError> {
     let not_xrd = BucketFactory::create_fungible_bucket(random_resource.resource_address(&mut env)?, dec!(50), Mock, &mut env)?;
 
     // Act
-    let result_too_little_xrd = component.get_nft_proof(Some(too_little_xrd), &mut env);
-    let result_not_xrd = component.get_nft_proof(Some(not_xrd), &mut env);
+    let result_too_little_xrd = component.get_nft_proof(Some(too_little_xrd), None, &mut env);
+    let result_not_xrd = component.get_nft_proof(Some(not_xrd), None, &mut env);
 
     // Assert
     assert!(
@@ -340,7 +353,7 @@ fn cannot_get_proof_without_payment_if_payment_required() -> Result<(), RuntimeE
         create_environment_with_fee(XRD, dec!(50), 30)?;
 
     // Act
-    let result = component.get_nft_proof(None, &mut env);
+    let result = component.get_nft_proof(None, None, &mut env);
 
     // Assert
     assert!(
@@ -369,7 +382,7 @@ fn cannot_get_proof_outside_timeframe() -> Result<(), RuntimeError> {
     env.set_current_time(future_timestamp);
 
     // Act
-    let result = component.get_nft_proof(Some(payment), &mut env);
+    let result = component.get_nft_proof(Some(payment), None, &mut env);
 
     // Assert
     assert!(result.is_err(), "Was able to get proof after timeframe!");
@@ -377,124 +390,2470 @@ fn cannot_get_proof_outside_timeframe() -> Result<(), RuntimeError> {
     Ok(())
 }
 
-fn create_environment_with_fee(
-    fee_resource: ResourceAddress,
-    fee_amount: Decimal,
-    days_in_future: i64,
-) -> Result<
-    (
-        TestEnvironment<InMemorySubstateDatabase>,
-        FlashProof,
-        Bucket,
-        NonFungibleGlobalId
-    ),
-    RuntimeError,
-> {
+#[test]
+fn operator_can_update_fee_and_end_timestamp() -> Result<(), RuntimeError> {
+    // Arrange
     let mut env = TestEnvironment::new();
     let package_address =
         PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let operator_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1, &mut env)?;
+    let operator_resource = operator_badge.resource_address(&mut env)?;
 
-    // Create timestamp
-    let timestamp = env.get_current_time().add_days(days_in_future).unwrap();
+    let options = InstantiateOptions {
+        operator_rule: Some(rule!(require(operator_resource))),
+        ..Default::default()
+    };
+    let (mut env, mut component, _, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
 
-    // Create fake nft
-    let nft = NonFungibleBucket(
-        ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
-            vec![FakeNFT {
-                name: "My Fake NFT".to_string(),
-            }],
-            &mut env,
-        )?,
+    let operator_proof = operator_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(operator_proof, &mut env)?;
+
+    let future_timestamp = env.get_current_time().add_days(60).unwrap();
+
+    // Act
+    component.update_fee(dec!(100), &mut env)?;
+    component.update_end_timestamp(future_timestamp, &mut env)?;
+
+    // Assert
+    let (new_fee, new_end_timestamp) = env
+        .with_component_state::<FlashProofState, _, _, _>(component, |state, _env| {
+            (state.fee_info.clone().unwrap().amount, state.end_timestamp)
+        })?;
+
+    assert!(new_fee == dec!(100), "Fee was not correctly updated");
+    assert!(new_end_timestamp == future_timestamp, "Timestamp was not properly updated!");
+
+    Ok(())
+}
+
+#[test]
+fn operator_cannot_withdraw_nft_or_fees() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let operator_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1, &mut env)?;
+    let operator_resource = operator_badge.resource_address(&mut env)?;
+
+    let options = InstantiateOptions {
+        operator_rule: Some(rule!(require(operator_resource))),
+        ..Default::default()
+    };
+    let (mut env, mut component, _, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let _ = component.get_nft_proof(Some(payment), None, &mut env)?;
+
+    let operator_proof = operator_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(operator_proof, &mut env)?;
+
+    // Act
+    let nft_result = component.withdraw_nft(&mut env);
+    let fee_result = component.withdraw_fees(&mut env);
+
+    // Assert
+    assert!(nft_result.is_err(), "Operator was unexpectedly able to withdraw the NFT");
+    assert!(fee_result.is_err(), "Operator was unexpectedly able to withdraw fees");
+
+    Ok(())
+}
+
+#[test]
+fn can_manage_component_with_non_fungible_owner_badge() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let badge_resource = FlashProof::create_owner_badge_resource(package_address, &mut env)?;
+
+    let options = InstantiateOptions {
+        owner_badge_resource: Some(badge_resource),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, global_id) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    assert!(
+        owner_badge.resource_address(&mut env)? == badge_resource,
+        "Owner badge was not minted from the shared badge resource"
     );
 
-    // Get NFT address
-    let nft_address = nft.0.resource_address(&mut env)?;
+    let owner_proof = owner_badge.create_proof_of_all(&mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
 
-    // Get NonFungibleLocalId
-    let nflid = nft
-        .0 // Again the .0 needed??
-        .non_fungible_local_ids(&mut env)?
-        .first()
-        .unwrap()
-        .clone();
-    
-    // Create NonFungibleGlobalId
-    let global_id = NonFungibleGlobalId::new(
-        nft_address,
-        nflid
+    // Act
+    component.update_owner_badge_label("My lending component".to_string(), &mut env)?;
+    let returned_nft = component.withdraw_nft(&mut env)?;
+
+    // Assert
+    let badge_id = owner_badge.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+    let badge_data: OwnerBadgeData = scrypto_decode(&env.call_method(
+        badge_resource.as_node_id(),
+        NON_FUNGIBLE_RESOURCE_MANAGER_GET_NON_FUNGIBLE_IDENT,
+        scrypto_encode(&NonFungibleResourceManagerGetNonFungibleInput { id: badge_id }).unwrap(),
+    )?)
+    .unwrap();
+    let component_address = ComponentAddress::try_from(component.0.as_bytes()).unwrap();
+
+    assert!(
+        returned_nft.0.resource_address(&mut env)? == global_id.resource_address(),
+        "Got back an NFT with a different resource address"
     );
+    assert!(badge_data.component == component_address, "Owner badge names the wrong component");
+    assert!(badge_data.nft_id == global_id, "Owner badge names the wrong NFT");
+    assert!(badge_data.created_at == env.get_current_time(), "Owner badge has the wrong creation time");
+    assert!(badge_data.label == "My lending component", "Owner badge label was not updated");
 
-    // Fee info
-    let fee_info = FeeInfo {
-        resource: fee_resource,
-        amount: fee_amount
+    Ok(())
+}
+
+#[test]
+fn non_fungible_owner_badge_only_controls_its_own_component() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let badge_resource = FlashProof::create_owner_badge_resource(package_address, &mut env)?;
+
+    let options = InstantiateOptions {
+        owner_badge_resource: Some(badge_resource),
+        ..Default::default()
     };
+    let (env, _, first_owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, options.clone())?;
+    let (mut env, mut second_component, _, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
 
-    // Instantiate component with the owner resource address and the NFT
-    let (component, owner_badge) = FlashProof::instantiate(
-        nft,
-        Some(fee_info),
-        timestamp,
-        package_address,
-        &mut env,
-    )?;
+    let owner_proof = first_owner_badge.create_proof_of_all(&mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
 
-    Ok((env, component, owner_badge.into(), global_id))
+    // Act
+    let result = second_component.withdraw_nft(&mut env);
+
+    // Assert
+    assert!(
+        result.is_err(),
+        "Was able to withdraw the NFT with the owner badge of another component"
+    );
+
+    Ok(())
 }
 
-fn create_environment_without_fee(
-    days_in_future: i64,
-) -> Result<
-    (
-        TestEnvironment<InMemorySubstateDatabase>,
-        FlashProof,
-        Bucket,
-        NonFungibleGlobalId
-    ),
-    RuntimeError,
-> {
+#[test]
+fn cannot_update_label_of_fungible_owner_badge() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    // Act
+    let result = component.update_owner_badge_label("My lending component".to_string(), &mut env);
+
+    // Assert
+    assert!(
+        result.is_err(),
+        "Was unexpectedly able to update the label of a fungible owner badge"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_recover_owner_badge_after_delay() -> Result<(), RuntimeError> {
+    // Arrange
     let mut env = TestEnvironment::new();
     let package_address =
         PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let recovery_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1, &mut env)?;
+    let recovery_resource = recovery_badge.resource_address(&mut env)?;
+
+    let options = InstantiateOptions {
+        recovery: Some(RecoveryInfo {
+            rule: rule!(require(recovery_resource)),
+            delay: 3600,
+        }),
+        ..Default::default()
+    };
+    let (mut env, mut component, old_owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
 
-    // Create timestamp
-    let timestamp = env.get_current_time().add_days(days_in_future).unwrap();
+    let recovery_proof = recovery_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(recovery_proof, &mut env)?;
 
-    // Create fake nft
-    let nft = NonFungibleBucket(
-        ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
-            vec![FakeNFT {
-                name: "My Fake NFT".to_string(),
-            }],
-            &mut env,
-        )?,
+    // Act
+    component.initiate_recovery(&mut env)?;
+    let too_early_result = component.complete_recovery(&mut env);
+
+    let later_timestamp = env.get_current_time().add_seconds(3600).unwrap();
+    env.set_current_time(later_timestamp);
+    let new_owner_badge = component.complete_recovery(&mut env)?;
+
+    // Assert
+    assert!(too_early_result.is_err(), "Was able to complete recovery before the delay");
+
+    LocalAuthZone::drop_proofs(&mut env)?;
+    let old_owner_proof = old_owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(old_owner_proof, &mut env)?;
+    assert!(
+        component.withdraw_nft(&mut env).is_err(),
+        "Old owner badge is still the owner after recovery"
     );
 
-    // Get NFT address
-    let nft_address = nft.0.resource_address(&mut env)?;
+    LocalAuthZone::drop_proofs(&mut env)?;
+    let new_owner_proof = new_owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(new_owner_proof, &mut env)?;
+    component.withdraw_nft(&mut env)?;
 
-    // Get NonFungibleLocalId
-    let nflid = nft
-        .0 // Again the .0 needed??
-        .non_fungible_local_ids(&mut env)?
-        .first()
+    Ok(())
+}
+
+#[test]
+fn owner_can_cancel_recovery() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let recovery_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1, &mut env)?;
+    let recovery_resource = recovery_badge.resource_address(&mut env)?;
+
+    let options = InstantiateOptions {
+        recovery: Some(RecoveryInfo {
+            rule: rule!(require(recovery_resource)),
+            delay: 3600,
+        }),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    let recovery_proof = recovery_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(recovery_proof, &mut env)?;
+    component.initiate_recovery(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    // Act
+    component.cancel_recovery(&mut env)?;
+
+    let later_timestamp = env.get_current_time().add_seconds(3600).unwrap();
+    env.set_current_time(later_timestamp);
+    let result = component.complete_recovery(&mut env);
+
+    // Assert
+    assert!(result.is_err(), "Was able to complete a cancelled recovery");
+
+    Ok(())
+}
+
+#[test]
+fn anyone_can_settle_after_expiry() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let account = create_account(&mut env)?;
+
+    let options = InstantiateOptions {
+        settlement: Some(SettlementInfo {
+            account,
+            keeper_reward: Some(dec!(10)),
+        }),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, global_id) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let _ = component.get_nft_proof(Some(payment), None, &mut env)?;
+
+    let future_timestamp = env
+        .get_current_time()
+        .add_days(30)
         .unwrap()
-        .clone();
+        .add_seconds(1)
+        .unwrap();
+    env.set_current_time(future_timestamp);
 
-    // Create NonFungibleGlobalId
-    let global_id = NonFungibleGlobalId::new(
-        nft_address,
-        nflid
-    ); 
+    // Act
+    let reward = component.settle(&mut env)?;
 
-    // Instantiate component with the owner resource address and the NFT
-    let (component, owner_badge) = FlashProof::instantiate(
-        nft,
-        None,
-        timestamp,
-        package_address,
-        &mut env,
-    )?;
+    // Assert
+    assert!(
+        reward.unwrap().amount(&mut env)? == dec!(10),
+        "Did not get the keeper reward"
+    );
 
-    Ok((env, component, owner_badge.into(), global_id))
-}
\ No newline at end of file
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    assert!(
+        component.withdraw_nft(&mut env).is_err(),
+        "NFT is still in the component after settling"
+    );
+
+    // The return account received the NFT and the fees minus the keeper reward
+    let returned_nft: Bucket = scrypto_decode(&env.call_method(
+        account.as_node_id(),
+        ACCOUNT_WITHDRAW_NON_FUNGIBLES_IDENT,
+        scrypto_encode(&AccountWithdrawNonFungiblesInput {
+            resource_address: global_id.resource_address(),
+            ids: indexset!(global_id.local_id().clone()),
+        })
+        .unwrap(),
+    )?)
+    .unwrap();
+    let returned_fees: Bucket = scrypto_decode(&env.call_method(
+        account.as_node_id(),
+        ACCOUNT_WITHDRAW_IDENT,
+        scrypto_encode(&AccountWithdrawInput {
+            resource_address: XRD,
+            amount: dec!(40),
+        })
+        .unwrap(),
+    )?)
+    .unwrap();
+    let more_fees_result = env.call_method(
+        account.as_node_id(),
+        ACCOUNT_WITHDRAW_IDENT,
+        scrypto_encode(&AccountWithdrawInput {
+            resource_address: XRD,
+            amount: dec!("0.000000000000000001"),
+        })
+        .unwrap(),
+    );
+
+    assert!(
+        returned_nft.amount(&mut env)? == dec!(1),
+        "Return account did not receive the NFT"
+    );
+    assert!(
+        returned_fees.amount(&mut env)? == dec!(40),
+        "Return account did not receive the remaining fees"
+    );
+    assert!(more_fees_result.is_err(), "Return account received more than the remaining fees");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_settle_before_expiry() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let account = create_account(&mut env)?;
+
+    let options = InstantiateOptions {
+        settlement: Some(SettlementInfo {
+            account,
+            keeper_reward: None,
+        }),
+        ..Default::default()
+    };
+    let (mut env, mut component, _, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    // Act
+    let result = component.settle(&mut env);
+
+    // Assert
+    assert!(result.is_err(), "Was able to settle before the end timestamp");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_with_stale_heartbeat() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        heartbeat_interval: Some(86400),
+        ..Default::default()
+    };
+    let (mut env, mut component, _, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    let later_timestamp = env.get_current_time().add_days(2).unwrap();
+    env.set_current_time(later_timestamp);
+
+    // Act
+    let result = component.get_nft_proof(None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof, even though the heartbeat is stale");
+
+    Ok(())
+}
+
+#[test]
+fn heartbeat_keeps_proof_generation_alive() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        heartbeat_interval: Some(86400),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    let later_timestamp = env.get_current_time().add_days(2).unwrap();
+    env.set_current_time(later_timestamp);
+
+    // Act
+    component.heartbeat(&mut env)?;
+    let _ = component.get_nft_proof(None, None, &mut env)?;
+
+    // Assert
+    let last_heartbeat = component.get_last_heartbeat(&mut env)?;
+    assert!(
+        last_heartbeat == env.get_current_time(),
+        "Last heartbeat was not updated"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_flash_borrow_and_repay_nft() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, global_id) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+
+    // Act
+    let (nft, receipt) = component.flash_borrow_nft(None, &mut env)?;
+    let borrowed_nflid = nft
+        .0
+        .non_fungible_local_ids(&mut env)?
+        .first()
+        .unwrap()
+        .clone();
+    let remainder = component.flash_repay_nft(nft, receipt, Some(payment), &mut env)?;
+
+    // Assert
+    assert!(borrowed_nflid == *global_id.local_id(), "Borrowed some weird-ass NFT");
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(10),
+        "Did not get back the expected amount"
+    );
+
+    // The NFT is back, so proofs can be generated again
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let _ = component.get_nft_proof(Some(payment), None, &mut env)?;
+
+    Ok(())
+}
+
+#[test]
+fn cannot_repay_flash_loan_with_other_nft() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, _) =
+        create_environment_without_fee(30)?;
+
+    let other_nft = NonFungibleBucket(
+        ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+            vec![FakeNFT {
+                name: "Another Fake NFT".to_string(),
+            }],
+            &mut env,
+        )?,
+    );
+
+    // Act
+    let (_, receipt) = component.flash_borrow_nft(None, &mut env)?;
+    let result = component.flash_repay_nft(other_nft, receipt, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Was able to repay the flash loan with another NFT");
+
+    Ok(())
+}
+
+#[test]
+fn can_get_single_proof_of_several_nfts() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, ids) =
+        create_environment_with_nft_collection(Some(FeeInfo { resource: XRD, amount: dec!(50) }), 3)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(150), Mock, &mut env)?;
+
+    // Act
+    let (proof, remainder) = component.get_nft_proofs(ids.clone(), Some(payment), None, &mut env)?;
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(0),
+        "Did not pay the fee for every NFT"
+    );
+
+    let proof_nflids = proof.0.non_fungible_local_ids(&mut env)?;
+    assert!(proof_nflids.len() == 3, "Proof does not cover all NFTs");
+    assert!(
+        ids.iter().all(|id| proof_nflids.contains(id)),
+        "Proof does not cover the requested NFTs"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bundle_fee_replaces_fee_per_nft() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, ids) =
+        create_environment_with_nft_collection(Some(FeeInfo { resource: XRD, amount: dec!(50) }), 3)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(150), Mock, &mut env)?;
+
+    // Act
+    component.update_bundle_fee(Some(dec!(100)), &mut env)?;
+    let (_, remainder) = component.get_nft_proofs(ids, Some(payment), None, &mut env)?;
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(50),
+        "Did not pay the bundle fee"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proofs_of_nfts_not_held() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, global_id) =
+        create_environment_without_fee(30)?;
+
+    let mut ids = IndexSet::new();
+    ids.insert(global_id.local_id().clone());
+    ids.insert(NonFungibleLocalId::integer(1));
+
+    // Act
+    let result = component.get_nft_proofs(ids, None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof of an NFT the component does not hold");
+
+    Ok(())
+}
+
+#[test]
+fn collection_proofs_rotate_over_held_nfts() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, ids) =
+        create_environment_with_nft_collection(None, 3)?;
+    let nft_address = env
+        .with_component_state::<FlashProofState, _, _, _>(component, |state, _env| {
+            state.nft_id.resource_address()
+        })?;
+
+    // Act
+    let mut proven_ids = IndexSet::new();
+    for _ in 0..3 {
+        let (proof, _) = component.get_collection_proof(nft_address, None, None, &mut env)?;
+        let proof_nflids = proof.0.non_fungible_local_ids(&mut env)?;
+        assert!(proof_nflids.len() == 1, "Proof does not cover exactly one NFT");
+        proven_ids.insert(proof_nflids.first().unwrap().clone());
+    }
+
+    // Assert
+    assert!(proven_ids == ids, "Collection proofs did not rotate over all NFTs");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_collection_proof_of_other_resource() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, _) =
+        create_environment_without_fee(30)?;
+
+    // Act
+    let result = component.get_collection_proof(XRD, None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got collection proof of a resource the component does not hold");
+
+    Ok(())
+}
+
+#[test]
+fn can_get_nft_data() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let timestamp = env.get_current_time().add_days(30).unwrap();
+
+    let nft = ResourceBuilder::new_ruid_non_fungible(OwnerRole::None)
+        .metadata(metadata!(
+            init {
+                "name" => "Fake NFTs", locked;
+                "description" => "A collection of fake NFTs", locked;
+                "icon_url" => UncheckedUrl::of("https://example.com/icon.png"), locked;
+            }
+        ))
+        .mint_initial_supply(
+            vec![FakeNFT {
+                name: "My Fake NFT".to_string(),
+            }],
+            &mut env,
+        )?;
+    let global_id = NonFungibleGlobalId::new(
+        nft.resource_address(&mut env)?,
+        nft.non_fungible_local_ids(&mut env)?.first().unwrap().clone(),
+    );
+    let (component, _) = FlashProof::instantiate(
+        NonFungibleBucket(nft),
+        None,
+        timestamp,
+        package_address,
+        &mut env,
+    )?;
+
+    // Act
+    let info = component.get_nft_data(None, &mut env)?;
+
+    // Assert
+    assert!(info.id == global_id, "Got data of some weird-ass NFT");
+
+    let data: FakeNFT = scrypto_decode(&info.data).unwrap();
+    assert!(data.name == "My Fake NFT", "Got back the wrong NFT data");
+    assert!(info.name == Some("Fake NFTs".to_string()), "Got back the wrong name");
+    assert!(
+        info.description == Some("A collection of fake NFTs".to_string()),
+        "Got back the wrong description"
+    );
+    assert!(
+        info.icon_url == Some(UncheckedUrl::of("https://example.com/icon.png")),
+        "Got back the wrong icon URL"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn proof_generation_stops_when_nft_data_changes() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let nft = ResourceBuilder::new_ruid_non_fungible(OwnerRole::None)
+        .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+            non_fungible_data_updater => rule!(allow_all);
+            non_fungible_data_updater_updater => rule!(deny_all);
+        ))
+        .mint_initial_supply(
+            vec![MutableFakeNFT {
+                name: "My Fake NFT".to_string(),
+                banned: false,
+            }],
+            &mut env,
+        )?;
+    let nft_address = nft.resource_address(&mut env)?;
+    let nflid = nft
+        .non_fungible_local_ids(&mut env)?
+        .first()
+        .unwrap()
+        .clone();
+
+    let timestamp = env.get_current_time().add_days(30).unwrap();
+    let options = InstantiateOptions {
+        guard_nft_data: true,
+        ..Default::default()
+    };
+    let (mut component, owner_badge) = FlashProof::instantiate_with_options(
+        NonFungibleBucket(nft),
+        None,
+        timestamp,
+        options,
+        package_address,
+        &mut env,
+    )?;
+
+    let _ = component.get_nft_proof(None, None, &mut env)?;
+
+    // Act
+    env.call_method(
+        nft_address.as_node_id(),
+        NON_FUNGIBLE_RESOURCE_MANAGER_UPDATE_DATA_IDENT,
+        scrypto_encode(&NonFungibleResourceManagerUpdateDataInput {
+            id: nflid,
+            field_name: "banned".to_string(),
+            data: scrypto_decode(&scrypto_encode(&true).unwrap()).unwrap(),
+        })
+        .unwrap(),
+    )?;
+    let changed_result = component.get_nft_proof(None, None, &mut env);
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.acknowledge_nft_data_change(&mut env)?;
+
+    // Assert
+    assert!(changed_result.is_err(), "Got proof, even though the NFT data changed");
+
+    let _ = component.get_nft_proof(None, None, &mut env)?;
+
+    Ok(())
+}
+
+#[test]
+fn recallable_nft_is_flagged_or_rejected() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let nfts = ResourceBuilder::new_ruid_non_fungible(OwnerRole::None)
+        .recall_roles(recall_roles!(
+            recaller => rule!(allow_all);
+            recaller_updater => rule!(deny_all);
+        ))
+        .mint_initial_supply(
+            vec![
+                FakeNFT { name: "My Fake NFT".to_string() },
+                FakeNFT { name: "My Other Fake NFT".to_string() },
+            ],
+            &mut env,
+        )?;
+    let first_nft = nfts.take(dec!(1), &mut env)?;
+
+    let timestamp = env.get_current_time().add_days(30).unwrap();
+
+    // Act
+    let (flagged_component, _) = FlashProof::instantiate_with_options(
+        NonFungibleBucket(first_nft),
+        None,
+        timestamp,
+        InstantiateOptions::default(),
+        package_address,
+        &mut env,
+    )?;
+
+    let reject_result = FlashProof::instantiate_with_options(
+        NonFungibleBucket(nfts),
+        None,
+        timestamp,
+        InstantiateOptions {
+            risk_policy: RiskPolicy::Reject,
+            ..Default::default()
+        },
+        package_address,
+        &mut env,
+    );
+
+    // Assert
+    let (recallable, freezable) = env
+        .with_component_state::<FlashProofState, _, _, _>(flagged_component, |state, _env| {
+            (state.risk_flags.recallable, state.risk_flags.freezable)
+        })?;
+    assert!(recallable, "Recallable NFT was not flagged");
+    assert!(!freezable, "Non-freezable NFT was flagged");
+
+    assert!(reject_result.is_err(), "Recallable NFT was not rejected");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_from_manifest_when_callers_are_restricted() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    // Act
+    component.add_allowed_caller(AllowedCaller::Package(FAUCET_PACKAGE), &mut env)?;
+    let restricted_result = component.get_nft_proof(None, None, &mut env);
+
+    component.remove_allowed_caller(AllowedCaller::Package(FAUCET_PACKAGE), &mut env)?;
+    let unrestricted_result = component.get_nft_proof(None, None, &mut env);
+
+    // Assert
+    assert!(
+        restricted_result.is_err(),
+        "Got proof, even though the caller is not allowed"
+    );
+    assert!(
+        unrestricted_result.is_ok(),
+        "Did not get proof after removing the caller restriction"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn allowed_component_and_blueprint_can_get_proof() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+    let component_address = ComponentAddress::try_from(component.0.as_bytes()).unwrap();
+
+    let mocks_package = publish_mocks(&mut env)?;
+    let allowed_borrower = create_mock_borrower(&mut env, mocks_package)?;
+    let other_borrower = create_mock_borrower(&mut env, mocks_package)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    let borrow_args = scrypto_encode(&(component_address,)).unwrap();
+
+    // Act
+    component.add_allowed_caller(AllowedCaller::Component(allowed_borrower), &mut env)?;
+    let allowed_component_result = env.call_method(allowed_borrower.as_node_id(), "borrow", borrow_args.clone());
+    let other_component_result = env.call_method(other_borrower.as_node_id(), "borrow", borrow_args.clone());
+
+    component.add_allowed_caller(
+        AllowedCaller::Blueprint(BlueprintId::new(&mocks_package, "MockBorrower")),
+        &mut env,
+    )?;
+    let allowed_blueprint_result = env.call_method(other_borrower.as_node_id(), "borrow", borrow_args);
+
+    // Assert
+    assert!(allowed_component_result.is_ok(), "Allowed component did not get proof");
+    assert!(other_component_result.is_err(), "Component that is not allowed got proof");
+    assert!(allowed_blueprint_result.is_ok(), "Component of allowed blueprint did not get proof");
+
+    Ok(())
+}
+
+#[test]
+fn can_call_allowed_target_with_proof() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, global_id) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+    let gate = create_mock_gate(&mut env, global_id)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.add_allowed_target(
+        TargetMethod {
+            component: gate,
+            method: "enter".to_string(),
+        },
+        &mut env,
+    )?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let (output, remainder) =
+        component.call_with_proof(gate, "enter".to_string(), scrypto_args!(), Some(payment), None, &mut env)?;
+
+    // Assert
+    assert!(output.len() == 1, "Did not get back the output of the target");
+    assert!(
+        output[0].amount(&mut env)? == dec!(1),
+        "Got back the wrong output from the target"
+    );
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(0),
+        "Got back more than expected"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_call_allowed_target_returning_tuple_of_buckets() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, global_id) =
+        create_environment_without_fee(30)?;
+    let gate = create_mock_gate(&mut env, global_id)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.add_allowed_target(
+        TargetMethod {
+            component: gate,
+            method: "enter_with_guest".to_string(),
+        },
+        &mut env,
+    )?;
+
+    // Act
+    let (output, _) =
+        component.call_with_proof(gate, "enter_with_guest".to_string(), scrypto_args!(), None, None, &mut env)?;
+
+    // Assert
+    assert!(output.len() == 2, "Did not get back all buckets of the target");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_call_target_that_is_not_allowed() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, global_id) =
+        create_environment_without_fee(30)?;
+    let gate = create_mock_gate(&mut env, global_id)?;
+
+    // Act
+    let result = component.call_with_proof(gate, "enter".to_string(), scrypto_args!(), None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Was able to call a target that is not allowed");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_without_holding_required_token() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let governance_token = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1000, &mut env)?;
+    let governance_resource = governance_token.resource_address(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_holding_requirement(
+        Some(HoldingRequirement {
+            resource: governance_resource,
+            amount: dec!(100),
+            waives_fee: false,
+        }),
+        &mut env,
+    )?;
+
+    let too_little_proof = governance_token.create_proof_of_amount(dec!(99), &mut env)?;
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let second_payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let too_little_result =
+        component.get_nft_proof(Some(payment), Some(FungibleProof(too_little_proof)), &mut env);
+    let no_proof_result = component.get_nft_proof(Some(second_payment), None, &mut env);
+
+    // Assert
+    assert!(too_little_result.is_err(), "Got proof without holding enough of the required token");
+    assert!(no_proof_result.is_err(), "Got proof without a proof of holding");
+
+    Ok(())
+}
+
+#[test]
+fn holders_can_get_proof_without_fee() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let governance_token = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1000, &mut env)?;
+    let governance_resource = governance_token.resource_address(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_holding_requirement(
+        Some(HoldingRequirement {
+            resource: governance_resource,
+            amount: dec!(100),
+            waives_fee: true,
+        }),
+        &mut env,
+    )?;
+
+    let holding_proof = governance_token.create_proof_of_amount(dec!(100), &mut env)?;
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof(Some(payment), Some(FungibleProof(holding_proof)), &mut env)?;
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(50),
+        "Holder was charged a fee"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_get_proof_with_permit_until_used_up() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let permit_key = Bls12381G1PrivateKey::from_u64(1).unwrap();
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_permit_key(Some(permit_key.public_key()), &mut env)?;
+
+    // The borrower is identified by a badge they hold
+    let borrower_badge = ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+        vec![FakeNFT {
+            name: "Partner badge".to_string(),
+        }],
+        &mut env,
+    )?;
+    let borrower = NonFungibleGlobalId::new(
+        borrower_badge.resource_address(&mut env)?,
+        borrower_badge
+            .non_fungible_local_ids(&mut env)?
+            .first()
+            .unwrap()
+            .clone(),
+    );
+    let borrower_proof = borrower_badge.create_proof_of_all(&mut env)?;
+    LocalAuthZone::push(borrower_proof, &mut env)?;
+
+    let permit = Permit {
+        borrower,
+        nonce: 1,
+        expires_at: env.get_current_time().add_days(1).unwrap(),
+        max_uses: 1,
+        price: dec!(10),
+    };
+    let component_address = ComponentAddress::try_from(component.0.as_bytes()).unwrap();
+    let signed_permit = SignedPermit {
+        signature: permit_key.sign_v1(&scrypto_encode(&(component_address, permit.clone())).unwrap()),
+        permit,
+    };
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let second_payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof_with_permit(signed_permit.clone(), Some(payment), None, &mut env)?;
+    let used_up_result = component.get_nft_proof_with_permit(signed_permit, Some(second_payment), None, &mut env);
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(40),
+        "Did not pay the permit price"
+    );
+    assert!(used_up_result.is_err(), "Was able to use a permit more often than allowed");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_with_permit_signed_by_other_key() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, global_id) =
+        create_environment_without_fee(30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_permit_key(Some(Bls12381G1PrivateKey::from_u64(1).unwrap().public_key()), &mut env)?;
+
+    let permit = Permit {
+        borrower: global_id,
+        nonce: 1,
+        expires_at: env.get_current_time().add_days(1).unwrap(),
+        max_uses: 1,
+        price: dec!(0),
+    };
+    let component_address = ComponentAddress::try_from(component.0.as_bytes()).unwrap();
+    let signed_permit = SignedPermit {
+        signature: Bls12381G1PrivateKey::from_u64(2)
+            .unwrap()
+            .sign_v1(&scrypto_encode(&(component_address, permit.clone())).unwrap()),
+        permit,
+    };
+
+    // Act
+    let result = component.get_nft_proof_with_permit(signed_permit, None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof with a permit signed by another key");
+
+    Ok(())
+}
+
+#[test]
+fn can_get_free_proof_with_access_code_until_used_up() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.add_access_code(
+        keccak256_hash("meetup-2026".as_bytes()),
+        AccessCode {
+            max_uses: 1,
+            expires_at: env.get_current_time().add_days(1).unwrap(),
+            free: true,
+        },
+        &mut env,
+    )?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof_with_code("meetup-2026".to_string(), None, None, &mut env)?;
+    let used_up_result = component.get_nft_proof_with_code("meetup-2026".to_string(), None, None, &mut env);
+
+    // Assert
+    assert!(remainder.is_none(), "Got a remainder without paying");
+    assert!(used_up_result.is_err(), "Was able to use an access code more often than allowed");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_with_removed_or_wrong_access_code() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    let code_hash = keccak256_hash("meetup-2026".as_bytes());
+    component.add_access_code(
+        code_hash,
+        AccessCode {
+            max_uses: 10,
+            expires_at: env.get_current_time().add_days(1).unwrap(),
+            free: true,
+        },
+        &mut env,
+    )?;
+
+    // Act
+    let wrong_code_result = component.get_nft_proof_with_code("meetup-2025".to_string(), None, None, &mut env);
+    component.remove_access_code(code_hash, &mut env)?;
+    let removed_code_result = component.get_nft_proof_with_code("meetup-2026".to_string(), None, None, &mut env);
+
+    // Assert
+    assert!(wrong_code_result.is_err(), "Got proof with a wrong access code");
+    assert!(removed_code_result.is_err(), "Got proof with a removed access code");
+
+    Ok(())
+}
+
+#[test]
+fn can_get_proof_with_grant_until_used_up() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    let grant = component.mint_grant(
+        env.get_current_time().add_days(1).unwrap(),
+        1,
+        Some(dec!(10)),
+        &mut env,
+    )?;
+
+    let grant_proof = grant.0.create_proof_of_all(&mut env)?;
+    let second_grant_proof = grant.0.create_proof_of_all(&mut env)?;
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof_with_grant(NonFungibleProof(grant_proof), Some(payment), None, &mut env)?;
+    let used_up_result = component.get_nft_proof_with_grant(NonFungibleProof(second_grant_proof), None, None, &mut env);
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(40),
+        "Did not pay the fee override"
+    );
+    assert!(used_up_result.is_err(), "Was able to use a grant more often than allowed");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_with_revoked_grant() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    let grant = component.mint_grant(
+        env.get_current_time().add_days(1).unwrap(),
+        10,
+        None,
+        &mut env,
+    )?;
+    let grant_id = grant.0.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+    component.revoke_grant(grant_id, &mut env)?;
+
+    let grant_proof = grant.0.create_proof_of_all(&mut env)?;
+
+    // Act
+    let result = component.get_nft_proof_with_grant(NonFungibleProof(grant_proof), None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof with a revoked grant");
+
+    Ok(())
+}
+
+#[test]
+fn can_redeem_approved_access_request() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        approval_window: Some(3600),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+    let (receipt, remainder) = component.request_access(Some(payment), "Trading competition".to_string(), &mut env)?;
+    let request_id = receipt.0.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    // Act
+    let unapproved_result = component.get_nft_proof(None, None, &mut env);
+    component.approve_request(request_id, &mut env)?;
+    let _ = component.redeem_request(receipt, None, &mut env)?;
+    let fees = component.withdraw_fees(&mut env)?;
+
+    // Assert
+    assert!(unapproved_result.is_err(), "Got proof without an approved request");
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(10),
+        "Did not escrow the fee"
+    );
+    assert!(
+        fees.0.amount(&mut env)? == dec!(50),
+        "Did not take the escrowed fee"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_proof_with_access_code_while_approval_is_required() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        approval_window: Some(3600),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.add_access_code(
+        keccak256_hash("meetup-2026".as_bytes()),
+        AccessCode {
+            max_uses: 1,
+            expires_at: env.get_current_time().add_days(1).unwrap(),
+            free: true,
+        },
+        &mut env,
+    )?;
+
+    // Act
+    let result = component.get_nft_proof_with_code("meetup-2026".to_string(), None, None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Got proof with an access code without an approved request");
+
+    Ok(())
+}
+
+#[test]
+fn can_reclaim_escrow_of_rejected_access_request() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        approval_window: Some(3600),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let (receipt, _) = component.request_access(Some(payment), "Trading competition".to_string(), &mut env)?;
+    let request_id = receipt.0.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.reject_request(request_id, &mut env)?;
+
+    // Act
+    let refund = component.reclaim_request(receipt, &mut env)?;
+
+    // Assert
+    assert!(
+        refund.unwrap().amount(&mut env)? == dec!(50),
+        "Did not get the escrowed fee back"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_get_proof_with_issuance_receipt() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, global_id) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder, receipt) = component.get_nft_proof_with_receipt(Some(payment), None, &mut env)?;
+
+    // Assert
+    let receipt_id = receipt.0.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+    let receipt_data: IssuanceReceipt = scrypto_decode(&env.call_method(
+        receipt.0.resource_address(&mut env)?.as_node_id(),
+        NON_FUNGIBLE_RESOURCE_MANAGER_GET_NON_FUNGIBLE_IDENT,
+        scrypto_encode(&NonFungibleResourceManagerGetNonFungibleInput { id: receipt_id }).unwrap(),
+    )?)
+    .unwrap();
+
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(10),
+        "Did not get back the expected amount"
+    );
+    assert!(receipt_data.nft_id == global_id, "Receipt is for the wrong NFT");
+    assert!(receipt_data.fee_resource == Some(XRD), "Receipt has the wrong fee resource");
+    assert!(receipt_data.amount_paid == dec!(50), "Receipt has the wrong amount paid");
+
+    Ok(())
+}
+
+#[test]
+fn can_read_usage_stats_and_issuance_log() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, _, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let first_payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let second_payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let _ = component.get_nft_proof(Some(first_payment), None, &mut env)?;
+    let _ = component.get_nft_proof(Some(second_payment), None, &mut env)?;
+
+    let stats = component.get_usage_stats(&mut env)?;
+    let fees_collected = component.get_fees_collected(XRD, &mut env)?;
+    let first_page = component.get_issuance_log(0, 1, &mut env)?;
+    let second_page = component.get_issuance_log(1, 10, &mut env)?;
+
+    // Assert
+    assert!(stats.total_proofs == 2, "Did not count the issued proofs");
+    assert!(stats.first_issued_at.is_some() && stats.last_issued_at.is_some(), "Did not record issuance times");
+    assert!(fees_collected == dec!(100), "Did not count the fees collected");
+    assert!(first_page.len() == 1 && second_page.len() == 1, "Did not page the issuance log");
+    assert!(first_page[0].kind == IssuanceKind::Proof, "Logged the wrong kind of issuance");
+
+    Ok(())
+}
+
+#[test]
+fn can_get_more_proofs_for_free_when_charging_once_per_transaction() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        transaction_policy: TransactionPolicy::ChargeOnce,
+        ..Default::default()
+    };
+    let (mut env, mut component, _, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let _ = component.get_nft_proof(Some(payment), None, &mut env)?;
+    let second_result = component.get_nft_proof(None, None, &mut env);
+
+    // Assert
+    assert!(second_result.is_ok(), "Had to pay twice in the same transaction");
+
+    Ok(())
+}
+
+#[test]
+fn grant_fee_override_follows_charge_once_policy() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        transaction_policy: TransactionPolicy::ChargeOnce,
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    let grant = component.mint_grant(
+        env.get_current_time().add_days(1).unwrap(),
+        2,
+        Some(dec!(10)),
+        &mut env,
+    )?;
+
+    let grant_proof = grant.0.create_proof_of_all(&mut env)?;
+    let second_grant_proof = grant.0.create_proof_of_all(&mut env)?;
+    let grant_payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+    let regular_payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) =
+        component.get_nft_proof_with_grant(NonFungibleProof(grant_proof), Some(grant_payment), None, &mut env)?;
+    let unpaid_result = component.get_nft_proof(None, None, &mut env);
+    let _ = component.get_nft_proof(Some(regular_payment), None, &mut env)?;
+    let second_grant_result =
+        component.get_nft_proof_with_grant(NonFungibleProof(second_grant_proof), None, None, &mut env);
+    let fees_collected = component.get_fees_collected(XRD, &mut env)?;
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(40),
+        "Did not pay the fee override"
+    );
+    assert!(unpaid_result.is_err(), "Paying a fee override waived the regular fee");
+    assert!(second_grant_result.is_ok(), "Had to pay the fee override after paying the regular fee");
+    assert!(fees_collected == dec!(60), "Did not charge the fee override and the regular fee once");
+
+    Ok(())
+}
+
+#[test]
+fn free_permit_does_not_waive_fee_when_charging_once() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        transaction_policy: TransactionPolicy::ChargeOnce,
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let permit_key = Bls12381G1PrivateKey::from_u64(1).unwrap();
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_permit_key(Some(permit_key.public_key()), &mut env)?;
+
+    let borrower_badge = ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+        vec![FakeNFT {
+            name: "Partner badge".to_string(),
+        }],
+        &mut env,
+    )?;
+    let borrower = NonFungibleGlobalId::new(
+        borrower_badge.resource_address(&mut env)?,
+        borrower_badge
+            .non_fungible_local_ids(&mut env)?
+            .first()
+            .unwrap()
+            .clone(),
+    );
+    let borrower_proof = borrower_badge.create_proof_of_all(&mut env)?;
+    LocalAuthZone::push(borrower_proof, &mut env)?;
+
+    let permit = Permit {
+        borrower,
+        nonce: 1,
+        expires_at: env.get_current_time().add_days(1).unwrap(),
+        max_uses: 1,
+        price: dec!(0),
+    };
+    let component_address = ComponentAddress::try_from(component.0.as_bytes()).unwrap();
+    let signed_permit = SignedPermit {
+        signature: permit_key.sign_v1(&scrypto_encode(&(component_address, permit.clone())).unwrap()),
+        permit,
+    };
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(50), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof_with_permit(signed_permit, Some(payment), None, &mut env)?;
+    let unpaid_result = component.get_nft_proof(None, None, &mut env);
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(50),
+        "Paid for a free permit"
+    );
+    assert!(unpaid_result.is_err(), "A free permit waived the regular fee");
+
+    Ok(())
+}
+
+#[test]
+fn fee_waived_by_holding_does_not_waive_later_fees_when_charging_once() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        transaction_policy: TransactionPolicy::ChargeOnce,
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let governance_token = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(1000, &mut env)?;
+    let governance_resource = governance_token.resource_address(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_holding_requirement(
+        Some(HoldingRequirement {
+            resource: governance_resource,
+            amount: dec!(100),
+            waives_fee: true,
+        }),
+        &mut env,
+    )?;
+
+    let holding_proof = governance_token.create_proof_of_amount(dec!(100), &mut env)?;
+
+    // Act
+    let _ = component.get_nft_proof(None, Some(FungibleProof(holding_proof)), &mut env)?;
+    component.update_holding_requirement(None, &mut env)?;
+    let unpaid_result = component.get_nft_proof(None, None, &mut env);
+
+    // Assert
+    assert!(unpaid_result.is_err(), "A fee waived by holding waived the regular fee");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_get_second_proof_with_single_issuance_per_transaction() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        transaction_policy: TransactionPolicy::SingleIssuance,
+        ..Default::default()
+    };
+    let (mut env, mut component, _, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    // Act
+    let _ = component.get_nft_proof(None, None, &mut env)?;
+    let second_result = component.get_nft_proof(None, None, &mut env);
+
+    // Assert
+    assert!(second_result.is_err(), "Got a second proof in the same transaction");
+
+    Ok(())
+}
+
+#[test]
+fn can_pay_fee_denominated_in_reference_unit() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    // 1 XRD is worth 0.05 in the reference unit, so a fee of 2.5 is 50 XRD
+    let oracle = create_mock_oracle(&mut env)?;
+    let now = env.get_current_time();
+    env.call_method(
+        oracle.as_node_id(),
+        "set_price",
+        scrypto_encode(&(XRD, dec!("0.05"), now)).unwrap(),
+    )?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_oracle_pricing(
+        Some(OraclePricing {
+            oracle,
+            reference_amount: dec!("2.5"),
+            max_age: 3600,
+            fallback_price: None,
+        }),
+        &mut env,
+    )?;
+
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof(Some(payment), None, &mut env)?;
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(10),
+        "Did not convert the fee at the oracle price"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn uses_fallback_price_when_oracle_price_is_stale() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let oracle = create_mock_oracle(&mut env)?;
+    let stale_time = env.get_current_time().add_days(-2).unwrap();
+    env.call_method(
+        oracle.as_node_id(),
+        "set_price",
+        scrypto_encode(&(XRD, dec!("0.05"), stale_time)).unwrap(),
+    )?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_oracle_pricing(
+        Some(OraclePricing {
+            oracle,
+            reference_amount: dec!("2.5"),
+            max_age: 3600,
+            fallback_price: None,
+        }),
+        &mut env,
+    )?;
+    let stale_result = component.get_nft_proof(None, None, &mut env);
+
+    component.update_oracle_pricing(
+        Some(OraclePricing {
+            oracle,
+            reference_amount: dec!("2.5"),
+            max_age: 3600,
+            fallback_price: Some(dec!("0.1")),
+        }),
+        &mut env,
+    )?;
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof(Some(payment), None, &mut env)?;
+
+    // Assert
+    assert!(stale_result.is_err(), "Used a stale oracle price");
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(35),
+        "Did not convert the fee at the fallback price"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cannot_pay_fee_at_oracle_price_from_the_future() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let oracle = create_mock_oracle(&mut env)?;
+    let future_time = env.get_current_time().add_days(1).unwrap();
+    env.call_method(
+        oracle.as_node_id(),
+        "set_price",
+        scrypto_encode(&(XRD, dec!("0.05"), future_time)).unwrap(),
+    )?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_oracle_pricing(
+        Some(OraclePricing {
+            oracle,
+            reference_amount: dec!("2.5"),
+            max_age: 3600,
+            fallback_price: None,
+        }),
+        &mut env,
+    )?;
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+
+    // Act
+    let result = component.get_nft_proof(Some(payment), None, &mut env);
+
+    // Assert
+    assert!(result.is_err(), "Used an oracle price from the future");
+
+    Ok(())
+}
+
+#[test]
+fn escrows_fee_of_access_request_at_oracle_price() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        approval_window: Some(3600),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, Some(FeeInfo { resource: XRD, amount: dec!(50) }), 30, options)?;
+
+    let oracle = create_mock_oracle(&mut env)?;
+    let now = env.get_current_time();
+    env.call_method(
+        oracle.as_node_id(),
+        "set_price",
+        scrypto_encode(&(XRD, dec!("0.05"), now)).unwrap(),
+    )?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_oracle_pricing(
+        Some(OraclePricing {
+            oracle,
+            reference_amount: dec!("2.5"),
+            max_age: 3600,
+            fallback_price: None,
+        }),
+        &mut env,
+    )?;
+    let payment = BucketFactory::create_fungible_bucket(XRD, dec!(60), Mock, &mut env)?;
+
+    // Act
+    let (_, remainder) = component.request_access(Some(payment), "Trading competition".to_string(), &mut env)?;
+
+    // Assert
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(10),
+        "Did not escrow the fee at the oracle price"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_pay_fee_in_other_resource_through_swap_hook() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    // The AMM gives 2 XRD per token, so the fee of 50 XRD takes 25 tokens
+    let amm = create_mock_amm(&mut env, dec!(2))?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_swap_hook(
+        Some(SwapHook {
+            component: amm,
+            max_slippage: dec!("0.01"),
+        }),
+        &mut env,
+    )?;
+
+    let payment = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(30, &mut env)?;
+
+    // Act
+    let (_, remainder, receipt) = component.get_nft_proof_with_receipt(Some(payment), None, &mut env)?;
+    let fees = component.withdraw_fees(&mut env)?;
+
+    // Assert
+    let receipt_id = receipt.0.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+    let receipt_data: IssuanceReceipt = scrypto_decode(&env.call_method(
+        receipt.0.resource_address(&mut env)?.as_node_id(),
+        NON_FUNGIBLE_RESOURCE_MANAGER_GET_NON_FUNGIBLE_IDENT,
+        scrypto_encode(&NonFungibleResourceManagerGetNonFungibleInput { id: receipt_id }).unwrap(),
+    )?)
+    .unwrap();
+
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(5),
+        "Did not swap just enough of the payment"
+    );
+    assert!(
+        fees.0.amount(&mut env)? == dec!(50),
+        "Did not receive the fee in the fee resource"
+    );
+    assert!(receipt_data.fee_resource == Some(XRD), "Receipt has the wrong fee resource");
+    assert!(receipt_data.amount_paid == dec!(50), "Receipt has the wrong amount paid");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_pay_fee_through_swap_hook_above_slippage_limit() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_fee(XRD, dec!(50), 30)?;
+
+    let amm = create_mock_amm(&mut env, dec!(2))?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_swap_hook(
+        Some(SwapHook {
+            component: amm,
+            max_slippage: dec!("0.01"),
+        }),
+        &mut env,
+    )?;
+
+    let mut tokens = ResourceBuilder::new_fungible(OwnerRole::None)
+        .mint_initial_supply(55, &mut env)?;
+
+    // Act
+    // 10% slippage makes the AMM quote more than the limit of 25.25 tokens
+    env.call_method(
+        amm.as_node_id(),
+        "set_slippage",
+        scrypto_encode(&(dec!("0.1"),)).unwrap(),
+    )?;
+    let payment = tokens.take(dec!(30), &mut env)?;
+    let above_input_limit_result = component.get_nft_proof(Some(payment), None, &mut env);
+
+    // A swap that returns 0.5% less than quoted returns less than the fee
+    env.call_method(
+        amm.as_node_id(),
+        "set_slippage",
+        scrypto_encode(&(dec!(0),)).unwrap(),
+    )?;
+    env.call_method(
+        amm.as_node_id(),
+        "set_shortfall",
+        scrypto_encode(&(dec!("0.005"),)).unwrap(),
+    )?;
+    let payment = tokens.take(dec!(25), &mut env)?;
+    let below_fee_result = component.get_nft_proof(Some(payment), None, &mut env);
+
+    // Assert
+    assert!(above_input_limit_result.is_err(), "Accepted a quote above the slippage limit");
+    assert!(below_fee_result.is_err(), "Accepted a swap that returned less than the fee");
+
+    Ok(())
+}
+
+#[test]
+fn can_pay_with_ticket_that_gets_burned() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let tickets = create_tickets(&mut env, vec![None, None])?;
+    let ticket_resource = tickets.resource_address(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_ticket_payment(
+        Some(TicketPayment {
+            resource: ticket_resource,
+            check_data: true,
+            burn: true,
+        }),
+        &mut env,
+    )?;
+
+    // Act
+    let without_ticket_result = component.get_nft_proof(None, None, &mut env);
+    let (_, remainder) = component.get_nft_proof(Some(tickets), None, &mut env)?;
+    let kept_tickets_result = component.withdraw_tickets(ticket_resource, &mut env);
+
+    // Assert
+    assert!(without_ticket_result.is_err(), "Got proof without a ticket");
+    assert!(
+        remainder.unwrap().amount(&mut env)? == dec!(1),
+        "Did not take exactly one ticket"
+    );
+    assert!(kept_tickets_result.is_err(), "Kept the ticket instead of burning it");
+
+    Ok(())
+}
+
+#[test]
+fn receipt_records_ticket_paid() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let tickets = create_tickets(&mut env, vec![None])?;
+    let ticket_resource = tickets.resource_address(&mut env)?;
+    let ticket_id = tickets.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_ticket_payment(
+        Some(TicketPayment {
+            resource: ticket_resource,
+            check_data: false,
+            burn: true,
+        }),
+        &mut env,
+    )?;
+
+    // Act
+    let (_, _, receipt) = component.get_nft_proof_with_receipt(Some(tickets), None, &mut env)?;
+
+    // Assert
+    let receipt_id = receipt.0.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+    let receipt_data: IssuanceReceipt = scrypto_decode(&env.call_method(
+        receipt.0.resource_address(&mut env)?.as_node_id(),
+        NON_FUNGIBLE_RESOURCE_MANAGER_GET_NON_FUNGIBLE_IDENT,
+        scrypto_encode(&NonFungibleResourceManagerGetNonFungibleInput { id: receipt_id }).unwrap(),
+    )?)
+    .unwrap();
+
+    assert!(receipt_data.fee_resource == Some(ticket_resource), "Receipt has the wrong fee resource");
+    assert!(receipt_data.amount_paid == dec!(1), "Receipt has the wrong amount paid");
+    assert!(receipt_data.ticket_id == Some(ticket_id), "Receipt has the wrong ticket");
+
+    Ok(())
+}
+
+#[test]
+fn cannot_combine_tickets_with_access_requests() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    let options = InstantiateOptions {
+        approval_window: Some(3600),
+        ..Default::default()
+    };
+    let (mut env, mut approval_component, approval_owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+    let (mut env, mut ticket_component, ticket_owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, InstantiateOptions::default())?;
+
+    let tickets = create_tickets(&mut env, vec![None])?;
+    let ticket_payment = TicketPayment {
+        resource: tickets.resource_address(&mut env)?,
+        check_data: false,
+        burn: true,
+    };
+
+    let approval_owner_proof = approval_owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(approval_owner_proof, &mut env)?;
+    let ticket_owner_proof = ticket_owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(ticket_owner_proof, &mut env)?;
+    ticket_component.update_ticket_payment(Some(ticket_payment.clone()), &mut env)?;
+
+    // Act
+    let tickets_result = approval_component.update_ticket_payment(Some(ticket_payment), &mut env);
+    let approval_result = ticket_component.update_approval_window(Some(3600), &mut env);
+
+    // Assert
+    assert!(tickets_result.is_err(), "Accepted tickets while access requests are taken");
+    assert!(approval_result.is_err(), "Took access requests while tickets are accepted");
+
+    Ok(())
+}
+
+#[test]
+fn can_keep_tickets_but_not_accept_expired_ones() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let expired = env.get_current_time().add_days(-1).unwrap();
+    let valid_ticket = create_tickets(&mut env, vec![None])?;
+    let ticket_resource = valid_ticket.resource_address(&mut env)?;
+    let expired_ticket = create_tickets(&mut env, vec![Some(expired)])?;
+    let expired_ticket_resource = expired_ticket.resource_address(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+
+    // Act
+    component.update_ticket_payment(
+        Some(TicketPayment {
+            resource: ticket_resource,
+            check_data: true,
+            burn: false,
+        }),
+        &mut env,
+    )?;
+    let _ = component.get_nft_proof(Some(valid_ticket), None, &mut env)?;
+    let kept_tickets = component.withdraw_tickets(ticket_resource, &mut env)?;
+
+    component.update_ticket_payment(
+        Some(TicketPayment {
+            resource: expired_ticket_resource,
+            check_data: true,
+            burn: false,
+        }),
+        &mut env,
+    )?;
+    let expired_result = component.get_nft_proof(Some(expired_ticket), None, &mut env);
+
+    // Assert
+    assert!(
+        kept_tickets.0.amount(&mut env)? == dec!(1),
+        "Did not keep the ticket"
+    );
+    assert!(expired_result.is_err(), "Accepted an expired ticket");
+
+    Ok(())
+}
+
+#[test]
+fn picks_valid_ticket_from_several() -> Result<(), RuntimeError> {
+    // Arrange
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_without_fee(30)?;
+
+    let expired = env.get_current_time().add_days(-1).unwrap();
+    let tickets = create_tickets(&mut env, vec![Some(expired), None])?;
+    let ticket_resource = tickets.resource_address(&mut env)?;
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_ticket_payment(
+        Some(TicketPayment {
+            resource: ticket_resource,
+            check_data: true,
+            burn: true,
+        }),
+        &mut env,
+    )?;
+
+    // Act
+    let (_, remainder) = component.get_nft_proof(Some(tickets), None, &mut env)?;
+
+    // Assert
+    let remainder = remainder.unwrap();
+    let remaining_id = remainder.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+    let remaining_data: ProofTicket = scrypto_decode(&env.call_method(
+        ticket_resource.as_node_id(),
+        NON_FUNGIBLE_RESOURCE_MANAGER_GET_NON_FUNGIBLE_IDENT,
+        scrypto_encode(&NonFungibleResourceManagerGetNonFungibleInput { id: remaining_id }).unwrap(),
+    )?)
+    .unwrap();
+
+    assert!(
+        remainder.amount(&mut env)? == dec!(1),
+        "Did not take exactly one ticket"
+    );
+    assert!(remaining_data.expires_at == Some(expired), "Took the expired ticket");
+
+    Ok(())
+}
+
+#[test]
+fn settle_returns_kept_tickets() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+    let account = create_account(&mut env)?;
+
+    let options = InstantiateOptions {
+        settlement: Some(SettlementInfo {
+            account,
+            keeper_reward: None,
+        }),
+        ..Default::default()
+    };
+    let (mut env, mut component, owner_badge, _) =
+        create_environment_with_options(env, package_address, None, 30, options)?;
+
+    let ticket = create_tickets(&mut env, vec![None])?;
+    let ticket_resource = ticket.resource_address(&mut env)?;
+    let ticket_id = ticket.non_fungible_local_ids(&mut env)?.first().unwrap().clone();
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.update_ticket_payment(
+        Some(TicketPayment {
+            resource: ticket_resource,
+            check_data: false,
+            burn: false,
+        }),
+        &mut env,
+    )?;
+    let _ = component.get_nft_proof(Some(ticket), None, &mut env)?;
+
+    let future_timestamp = env
+        .get_current_time()
+        .add_days(30)
+        .unwrap()
+        .add_seconds(1)
+        .unwrap();
+    env.set_current_time(future_timestamp);
+
+    // Act
+    let _ = component.settle(&mut env)?;
+
+    // Assert
+    let returned_ticket: Bucket = scrypto_decode(&env.call_method(
+        account.as_node_id(),
+        ACCOUNT_WITHDRAW_NON_FUNGIBLES_IDENT,
+        scrypto_encode(&AccountWithdrawNonFungiblesInput {
+            resource_address: ticket_resource,
+            ids: indexset!(ticket_id),
+        })
+        .unwrap(),
+    )?)
+    .unwrap();
+
+    assert!(
+        returned_ticket.amount(&mut env)? == dec!(1),
+        "Return account did not receive the kept ticket"
+    );
+    assert!(
+        component.withdraw_tickets(ticket_resource, &mut env).is_err(),
+        "Ticket is still in the component after settling"
+    );
+
+    Ok(())
+}
+
+fn create_environment_with_fee(
+    fee_resource: ResourceAddress,
+    fee_amount: Decimal,
+    days_in_future: i64,
+) -> Result<
+    (
+        TestEnvironment<InMemorySubstateDatabase>,
+        FlashProof,
+        Bucket,
+        NonFungibleGlobalId
+    ),
+    RuntimeError,
+> {
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    // Create timestamp
+    let timestamp = env.get_current_time().add_days(days_in_future).unwrap();
+
+    // Create fake nft
+    let nft = NonFungibleBucket(
+        ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+            vec![FakeNFT {
+                name: "My Fake NFT".to_string(),
+            }],
+            &mut env,
+        )?,
+    );
+
+    // Get NFT address
+    let nft_address = nft.0.resource_address(&mut env)?;
+
+    // Get NonFungibleLocalId
+    let nflid = nft
+        .0 // Again the .0 needed??
+        .non_fungible_local_ids(&mut env)?
+        .first()
+        .unwrap()
+        .clone();
+    
+    // Create NonFungibleGlobalId
+    let global_id = NonFungibleGlobalId::new(
+        nft_address,
+        nflid
+    );
+
+    // Fee info
+    let fee_info = FeeInfo {
+        resource: fee_resource,
+        amount: fee_amount
+    };
+
+    // Instantiate component with the owner resource address and the NFT
+    let (component, owner_badge) = FlashProof::instantiate(
+        nft,
+        Some(fee_info),
+        timestamp,
+        package_address,
+        &mut env,
+    )?;
+
+    Ok((env, component, owner_badge.into(), global_id))
+}
+
+fn create_environment_without_fee(
+    days_in_future: i64,
+) -> Result<
+    (
+        TestEnvironment<InMemorySubstateDatabase>,
+        FlashProof,
+        Bucket,
+        NonFungibleGlobalId
+    ),
+    RuntimeError,
+> {
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    // Create timestamp
+    let timestamp = env.get_current_time().add_days(days_in_future).unwrap();
+
+    // Create fake nft
+    let nft = NonFungibleBucket(
+        ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+            vec![FakeNFT {
+                name: "My Fake NFT".to_string(),
+            }],
+            &mut env,
+        )?,
+    );
+
+    // Get NFT address
+    let nft_address = nft.0.resource_address(&mut env)?;
+
+    // Get NonFungibleLocalId
+    let nflid = nft
+        .0 // Again the .0 needed??
+        .non_fungible_local_ids(&mut env)?
+        .first()
+        .unwrap()
+        .clone();
+
+    // Create NonFungibleGlobalId
+    let global_id = NonFungibleGlobalId::new(
+        nft_address,
+        nflid
+    ); 
+
+    // Instantiate component with the owner resource address and the NFT
+    let (component, owner_badge) = FlashProof::instantiate(
+        nft,
+        None,
+        timestamp,
+        package_address,
+        &mut env,
+    )?;
+
+    Ok((env, component, owner_badge.into(), global_id))
+}
+
+fn create_environment_with_options(
+    mut env: TestEnvironment<InMemorySubstateDatabase>,
+    package_address: PackageAddress,
+    fee_info: Option<FeeInfo>,
+    days_in_future: i64,
+    options: InstantiateOptions,
+) -> Result<
+    (
+        TestEnvironment<InMemorySubstateDatabase>,
+        FlashProof,
+        Bucket,
+        NonFungibleGlobalId
+    ),
+    RuntimeError,
+> {
+    // Create timestamp
+    let timestamp = env.get_current_time().add_days(days_in_future).unwrap();
+
+    // Create fake nft
+    let nft = NonFungibleBucket(
+        ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+            vec![FakeNFT {
+                name: "My Fake NFT".to_string(),
+            }],
+            &mut env,
+        )?,
+    );
+
+    // Create NonFungibleGlobalId
+    let nft_address = nft.0.resource_address(&mut env)?;
+    let nflid = nft
+        .0
+        .non_fungible_local_ids(&mut env)?
+        .first()
+        .unwrap()
+        .clone();
+    let global_id = NonFungibleGlobalId::new(
+        nft_address,
+        nflid
+    );
+
+    // Instantiate component with the supplied options
+    let (component, owner_badge) = FlashProof::instantiate_with_options(
+        nft,
+        fee_info,
+        timestamp,
+        options,
+        package_address,
+        &mut env,
+    )?;
+
+    Ok((env, component, owner_badge.into(), global_id))
+}
+
+fn create_account(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+) -> Result<ComponentAddress, RuntimeError> {
+    env.call_function_typed::<_, ComponentAddress>(
+        ACCOUNT_PACKAGE,
+        ACCOUNT_BLUEPRINT,
+        ACCOUNT_CREATE_ADVANCED_IDENT,
+        &AccountCreateAdvancedInput {
+            // Anyone can withdraw, so tests can check what was deposited
+            owner_role: OwnerRole::Fixed(rule!(allow_all)),
+            address_reservation: None,
+        },
+    )
+}
+
+fn create_environment_with_nft_collection(
+    fee_info: Option<FeeInfo>,
+    nft_count: usize,
+) -> Result<
+    (
+        TestEnvironment<InMemorySubstateDatabase>,
+        FlashProof,
+        Bucket,
+        IndexSet<NonFungibleLocalId>
+    ),
+    RuntimeError,
+> {
+    let mut env = TestEnvironment::new();
+    let package_address =
+        PackageFactory::compile_and_publish(this_package!(), &mut env, CompileProfile::Fast)?;
+
+    // Create timestamp
+    let timestamp = env.get_current_time().add_days(30).unwrap();
+
+    // Create a collection of fake nfts
+    let nfts = ResourceBuilder::new_ruid_non_fungible(OwnerRole::None).mint_initial_supply(
+        (0..nft_count)
+            .map(|i| FakeNFT {
+                name: format!("My Fake NFT #{}", i),
+            })
+            .collect::<Vec<_>>(),
+        &mut env,
+    )?;
+    let ids = nfts.non_fungible_local_ids(&mut env)?;
+
+    // Instantiate component with the first NFT and deposit the rest
+    let first_nft = nfts.take(dec!(1), &mut env)?;
+    let (mut component, owner_badge) = FlashProof::instantiate(
+        NonFungibleBucket(first_nft),
+        fee_info,
+        timestamp,
+        package_address,
+        &mut env,
+    )?;
+    let owner_badge: Bucket = owner_badge.into();
+
+    let owner_proof = owner_badge.create_proof_of_amount(dec!(1), &mut env)?;
+    LocalAuthZone::push(owner_proof, &mut env)?;
+    component.deposit_nfts(NonFungibleBucket(nfts), &mut env)?;
+    LocalAuthZone::drop_proofs(&mut env)?;
+
+    Ok((env, component, owner_badge, ids))
+}
+
+fn publish_mocks(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+) -> Result<PackageAddress, RuntimeError> {
+    PackageFactory::compile_and_publish(
+        Path::new(this_package!()).join("tests/assets/mocks"),
+        env,
+        CompileProfile::Fast,
+    )
+}
+
+fn create_mock_borrower(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+    mocks_package: PackageAddress,
+) -> Result<ComponentAddress, RuntimeError> {
+    env.call_function_typed::<_, ComponentAddress>(
+        mocks_package,
+        "MockBorrower",
+        "instantiate",
+        &(),
+    )
+}
+
+fn create_mock_gate(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+    required_nft: NonFungibleGlobalId,
+) -> Result<ComponentAddress, RuntimeError> {
+    let mocks_package = publish_mocks(env)?;
+
+    env.call_function_typed::<_, ComponentAddress>(
+        mocks_package,
+        "MockGate",
+        "instantiate",
+        &(required_nft,),
+    )
+}
+
+fn create_mock_oracle(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+) -> Result<ComponentAddress, RuntimeError> {
+    let mocks_package = publish_mocks(env)?;
+
+    env.call_function_typed::<_, ComponentAddress>(
+        mocks_package,
+        "MockOracle",
+        "instantiate",
+        &(),
+    )
+}
+
+fn create_mock_amm(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+    rate: Decimal,
+) -> Result<ComponentAddress, RuntimeError> {
+    let mocks_package = publish_mocks(env)?;
+    let liquidity = BucketFactory::create_fungible_bucket(XRD, dec!(1000), Mock, env)?;
+
+    env.call_function_typed::<_, ComponentAddress>(
+        mocks_package,
+        "MockAmm",
+        "instantiate",
+        &(liquidity, rate),
+    )
+}
+
+fn create_tickets(
+    env: &mut TestEnvironment<InMemorySubstateDatabase>,
+    expiries: Vec<Option<Instant>>,
+) -> Result<Bucket, RuntimeError> {
+    ResourceBuilder::new_ruid_non_fungible(OwnerRole::None)
+        .burn_roles(burn_roles!(
+            burner => rule!(allow_all);
+            burner_updater => rule!(deny_all);
+        ))
+        .mint_initial_supply(
+            expiries
+                .into_iter()
+                .map(|expires_at| ProofTicket {
+                    expires_at,
+                    component: None,
+                })
+                .collect::<Vec<_>>(),
+            env,
+        )
+}